@@ -0,0 +1,199 @@
+//! An attestation-bound, Noise-protected transport
+//!
+//! Wraps a `TcpStream` in a Noise handshake (pattern `NX`) whose responder
+//! payload carries the enclave's attestation evidence, binding the Noise
+//! static key to the attested enclave before any application traffic is
+//! allowed to flow. Once the handshake completes, `send_message` and
+//! `receive_message` behave like their plaintext counterparts in
+//! `super::tcp`, except that every frame is additionally encrypted and
+//! authenticated by the negotiated Noise transport state.
+//!
+//! This module is infrastructure only: nothing in this source tree calls
+//! `AttestedChannel`, and `super::tcp`'s plaintext `send_message`/
+//! `receive_message` have no in-tree callers either (the code that opens
+//! the runtime_manager socket and would choose between them lives outside
+//! this snapshot). Swapping that call site from `super::tcp` over to
+//! `AttestedChannel::accept`/`connect` is the follow-up needed before this
+//! actually protects any traffic.
+//!
+//! # Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! # Copyright and licensing
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for copyright
+//! and licensing information.
+
+use super::fd::{receive_buffer, send_buffer};
+use anyhow::{anyhow, Result};
+use bincode::{deserialize, serialize};
+use log::error;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use snow::{Builder, HandshakeState, TransportState};
+use std::net::TcpStream;
+
+/// The Noise protocol pattern used to establish the attested channel.
+///
+/// `Noise_NX_25519_ChaChaPoly_SHA256`: the initiator is anonymous, the
+/// responder (the enclave) reveals its static key during the handshake and
+/// authenticates it out-of-band by embedding the attestation token (which in
+/// turn commits to a hash of that static key) as the handshake payload.
+const NOISE_PATTERN: &str = "Noise_NX_25519_ChaChaPoly_SHA256";
+
+/// Something that can produce a fresh attestation token for this process,
+/// and can verify a token received from a peer.
+///
+/// Implemented by the `NativeAttestation`/`ProxyAttestation` machinery;
+/// kept as a trait here so that `io-utils` does not need to depend
+/// directly on the attestation crates.
+pub trait AttestationProvider {
+    /// Produce an attestation token whose user-data field is bound to
+    /// `static_key_hash` (the SHA-256 hash of our Noise static public key).
+    fn generate_token(&self, static_key_hash: &[u8; 32]) -> Result<Vec<u8>>;
+
+    /// Verify a peer-supplied attestation token, checking that the
+    /// embedded user-data field matches `expected_static_key_hash`.
+    /// Returns an error if the token is invalid, or if the embedded hash
+    /// does not match.
+    fn verify_token(&self, token: &[u8], expected_static_key_hash: &[u8; 32]) -> Result<()>;
+}
+
+/// A `TcpStream` wrapped in an attested Noise transport. All `send_message`
+/// and `receive_message` traffic is carried over the Noise transport state
+/// established in `accept` or `connect`. A channel returned by `connect` is
+/// a guarantee that the responder's Noise static key was attested by the
+/// provided `AttestationProvider`; `accept` does not authenticate the
+/// initiator, which stays anonymous under the `NX` pattern.
+pub struct AttestedChannel {
+    socket: TcpStream,
+    transport: TransportState,
+}
+
+impl AttestedChannel {
+    /// Perform the responder side of the handshake (run inside the
+    /// enclave): generate an ephemeral Noise static keypair, embed an
+    /// attestation token over its hash in the handshake payload, and
+    /// transition to the Noise transport phase.
+    pub fn accept(mut socket: TcpStream, attestation: &dyn AttestationProvider) -> Result<Self> {
+        let builder = Builder::new(NOISE_PATTERN.parse()?);
+        let keypair = builder.generate_keypair()?;
+        let static_key_hash = hash_static_key(&keypair.public);
+        let token = attestation.generate_token(&static_key_hash)?;
+
+        let mut handshake: HandshakeState = Builder::new(NOISE_PATTERN.parse()?)
+            .local_private_key(&keypair.private)
+            .build_responder()?;
+
+        // -> e
+        let mut buf = [0u8; 1024];
+        let msg = receive_buffer(&mut socket)?;
+        handshake.read_message(&msg, &mut buf)?;
+
+        // <- e, ee, s, es, payload (the attestation token)
+        let mut out = vec![0u8; 65535];
+        let len = handshake.write_message(&token, &mut out)?;
+        send_buffer(&mut socket, &out[..len])?;
+
+        let transport = handshake.into_transport_mode()?;
+        Ok(AttestedChannel { socket, transport })
+    }
+
+    /// Perform the initiator side of the handshake (run by the client):
+    /// verify the attestation token carried in the responder's handshake
+    /// payload, check that its embedded hash matches the responder's
+    /// static key, and only then transition to the Noise transport phase.
+    pub fn connect(mut socket: TcpStream, attestation: &dyn AttestationProvider) -> Result<Self> {
+        let mut handshake: HandshakeState =
+            Builder::new(NOISE_PATTERN.parse()?).build_initiator()?;
+
+        // -> e
+        let mut out = vec![0u8; 65535];
+        let len = handshake.write_message(&[], &mut out)?;
+        send_buffer(&mut socket, &out[..len])?;
+
+        // <- e, ee, s, es, payload
+        let msg = receive_buffer(&mut socket)?;
+        let mut token = vec![0u8; msg.len()];
+        let token_len = handshake.read_message(&msg, &mut token)?;
+        token.truncate(token_len);
+
+        let responder_static = handshake
+            .get_remote_static()
+            .ok_or_else(|| anyhow!("Noise handshake completed without a remote static key"))?;
+        let expected_hash = hash_static_key(responder_static);
+
+        attestation
+            .verify_token(&token, &expected_hash)
+            .map_err(|e| anyhow!("attestation token did not verify: {}", e))?;
+
+        let transport = handshake.into_transport_mode()?;
+        Ok(AttestedChannel { socket, transport })
+    }
+
+    /// Transmits a serialized message over the attested Noise transport.
+    ///
+    /// Fails if the message cannot be serialized, encrypted, or
+    /// transmitted.
+    pub fn send_message<T>(&mut self, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let plaintext = serialize(&data).map_err(|e| {
+            error!("Failed to serialize message.  Error produced: {}.", e);
+            e
+        })?;
+
+        let mut ciphertext = vec![0u8; plaintext.len() + 16];
+        let len = self
+            .transport
+            .write_message(&plaintext, &mut ciphertext)
+            .map_err(|e| anyhow!("Noise encryption failed: {}", e))?;
+        ciphertext.truncate(len);
+
+        send_buffer(&mut self.socket, &ciphertext).map_err(|e| {
+            error!("Failed to transmit message.  Error produced: {}.", e);
+            e
+        })?;
+
+        Ok(())
+    }
+
+    /// Receives and deserializes a message over the attested Noise
+    /// transport.
+    ///
+    /// Fails if no message can be received, if it cannot be decrypted, or
+    /// if the decrypted message cannot be deserialized.
+    pub fn receive_message<T>(&mut self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let ciphertext = receive_buffer(&mut self.socket).map_err(|e| {
+            error!("Failed to receive response.  Error produced: {}.", e);
+            e
+        })?;
+
+        let mut plaintext = vec![0u8; ciphertext.len()];
+        let len = self
+            .transport
+            .read_message(&ciphertext, &mut plaintext)
+            .map_err(|e| anyhow!("Noise decryption failed: {}", e))?;
+        plaintext.truncate(len);
+
+        let message: T = deserialize(&plaintext).map_err(|e| {
+            error!("Failed to deserialize response.  Error produced: {}.", e);
+            e
+        })?;
+
+        Ok(message)
+    }
+}
+
+/// Hash a Noise static public key, for binding into (and checking against)
+/// an attestation token's user-data field.
+fn hash_static_key(key: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(key);
+    hasher.finalize().into()
+}
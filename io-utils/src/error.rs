@@ -0,0 +1,61 @@
+//! Error types for the `io-utils` crate
+//!
+//! # Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! # Copyright and licensing
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for copyright
+//! and licensing information.
+
+use err_derive::Error;
+
+/// Errors arising from the raw socket read/write helpers in `fd`.
+#[derive(Debug, Error)]
+pub enum SocketError {
+    #[error(display = "SocketError: IOError: {:?}.", _0)]
+    IOError(#[error(source)] std::io::Error),
+    #[error(display = "SocketError: The socket was closed by the peer.")]
+    ConnectionClosed,
+    #[error(
+        display = "SocketError: Message of {} bytes exceeds the {}-byte limit.",
+        size,
+        limit
+    )]
+    MessageTooLarge { size: usize, limit: usize },
+}
+
+/// Granular errors for `send_message`/`receive_message`, so that callers can
+/// tell a serialization failure apart from a socket EOF, a connection
+/// reset, or an oversized frame, and react accordingly (retry vs. abort vs.
+/// treat as an attestation failure).
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error(display = "TransportError: Failed to serialize message: {:?}.", _0)]
+    Serialize(#[error(source)] bincode::Error),
+    #[error(display = "TransportError: Failed to deserialize message: {:?}.", _0)]
+    Deserialize(#[error(source)] bincode::Error),
+    #[error(display = "TransportError: IOError: {:?}.", _0)]
+    Io(#[error(source)] std::io::Error),
+    #[error(display = "TransportError: The connection was closed by the peer.")]
+    ConnectionClosed,
+    #[error(
+        display = "TransportError: Message of {} bytes exceeds the {}-byte limit.",
+        size,
+        limit
+    )]
+    MessageTooLarge { size: usize, limit: usize },
+}
+
+impl From<SocketError> for TransportError {
+    fn from(error: SocketError) -> Self {
+        match error {
+            SocketError::IOError(e) => TransportError::Io(e),
+            SocketError::ConnectionClosed => TransportError::ConnectionClosed,
+            SocketError::MessageTooLarge { size, limit } => {
+                TransportError::MessageTooLarge { size, limit }
+            }
+        }
+    }
+}
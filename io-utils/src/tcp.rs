@@ -1,5 +1,10 @@
 //! Common TCP socket-related functionality
 //!
+//! Plaintext, unauthenticated framing; see [`super::attested_channel`] for
+//! the Noise-protected, attestation-bound equivalent, which is meant to
+//! replace this module's use at the actual runtime_manager socket call
+//! site (outside this source tree) rather than run alongside it.
+//!
 //! # Authors
 //!
 //! The Veracruz Development Team.
@@ -9,8 +14,8 @@
 //! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for copyright
 //! and licensing information.
 
-use super::fd::{receive_buffer, send_buffer};
-use anyhow::Result;
+use super::error::TransportError;
+use super::fd::{receive_buffer_with_config, send_buffer, SocketConfig};
 use bincode::{deserialize, serialize};
 use log::error;
 use serde::{de::DeserializeOwned, Serialize};
@@ -20,43 +25,61 @@ use std::net::TcpStream;
 ///
 /// Fails if the message cannot be serialized, or if the serialized message
 /// cannot be transmitted.
-pub fn send_message<T>(socket: &mut TcpStream, data: T) -> Result<()>
+pub fn send_message<T>(socket: &mut TcpStream, data: T) -> Result<(), TransportError>
 where
     T: Serialize,
 {
     let message = serialize(&data).map_err(|e| {
         error!("Failed to serialize message.  Error produced: {}.", e);
 
-        e
+        TransportError::Serialize(e)
     })?;
 
     send_buffer(socket, &message).map_err(|e| {
         error!("Failed to transmit message.  Error produced: {}.", e);
 
-        e
+        TransportError::from(e)
     })?;
 
     Ok(())
 }
 
-/// Receives and deserializes a message via a socket.
+/// Receives and deserializes a message via a socket, using the default
+/// `SocketConfig` (and so its default `max_message_bytes` guard). See
+/// [`receive_message_with_config`] to customize the size limit.
 ///
 /// Fails if no message can be received, or if the received message cannot be
 /// deserialized.
-pub fn receive_message<T>(socket: &mut TcpStream) -> Result<T>
+pub fn receive_message<T>(socket: &mut TcpStream) -> Result<T, TransportError>
+where
+    T: DeserializeOwned,
+{
+    receive_message_with_config(socket, &SocketConfig::default())
+}
+
+/// Receives and deserializes a message via a socket, rejecting messages
+/// whose length prefix exceeds `config.max_message_bytes` before any body
+/// allocation takes place.
+///
+/// Fails if no message can be received, if the received message is too
+/// large, or if it cannot be deserialized.
+pub fn receive_message_with_config<T>(
+    socket: &mut TcpStream,
+    config: &SocketConfig,
+) -> Result<T, TransportError>
 where
     T: DeserializeOwned,
 {
-    let response = receive_buffer(socket).map_err(|e| {
+    let response = receive_buffer_with_config(socket, config).map_err(|e| {
         error!("Failed to receive response.  Error produced: {}.", e);
 
-        e
+        TransportError::from(e)
     })?;
 
     let message: T = deserialize(&response).map_err(|e| {
         error!("Failed to deserialize response.  Error produced: {}.", e);
 
-        e
+        TransportError::Deserialize(e)
     })?;
 
     Ok(message)
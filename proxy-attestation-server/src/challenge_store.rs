@@ -0,0 +1,186 @@
+//! Challenge freshness and replay protection for the attestation handshake
+//!
+//! The `NativeAttestation`/`ChallengeData`/`ProxyAttestation` flow generates
+//! a 128-bit challenge and a challenge ID, but on its own that gives no
+//! guarantee that a returned attestation document's nonce is fresh,
+//! single-use, or timely. `ChallengeStore` closes that gap: every issued
+//! challenge is recorded against its ID with an issue timestamp and a TTL.
+//! [`ChallengeStore::expected_nonce`] hands a caller the nonce that must
+//! appear in the attestation document itself — the exact-match check
+//! happens in the platform verifier that parses that document, not here —
+//! and [`ChallengeStore::consume`] atomically removes the entry once that
+//! check has passed, so the same challenge can never be accepted twice.
+//! Expired entries are garbage-collected on access.
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use err_derive::Error;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Error)]
+pub enum ChallengeStoreError {
+    #[error(display = "ChallengeStoreError: No challenge is outstanding for ID {}.", _0)]
+    UnknownChallengeId(i32),
+    #[error(display = "ChallengeStoreError: Challenge {} has expired.", _0)]
+    ChallengeExpired(i32),
+    #[error(display = "ChallengeStoreError: Failed to obtain lock: {:?}.", _0)]
+    LockError(String),
+}
+
+struct ChallengeEntry {
+    nonce: Vec<u8>,
+    issued_at: Instant,
+}
+
+/// Tracks outstanding attestation challenges, keyed by the `i32` challenge
+/// ID the root enclave hands back in `ChallengeData`.
+pub struct ChallengeStore {
+    ttl: Duration,
+    entries: Mutex<HashMap<i32, ChallengeEntry>>,
+}
+
+impl ChallengeStore {
+    /// Create an empty store whose entries expire `ttl` after they are
+    /// issued.
+    pub fn new(ttl: Duration) -> Self {
+        ChallengeStore {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record that `nonce` was issued under `challenge_id`, starting its TTL
+    /// clock now.
+    pub fn issue(&self, challenge_id: i32, nonce: Vec<u8>) -> Result<(), ChallengeStoreError> {
+        let mut entries = self.lock()?;
+        self.gc(&mut entries);
+        entries.insert(
+            challenge_id,
+            ChallengeEntry {
+                nonce,
+                issued_at: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Look up the nonce issued under `challenge_id`, without consuming the
+    /// entry, so a caller can hand it to the platform verifier that checks
+    /// it against the nonce actually embedded in the attestation document.
+    /// Errors if the challenge is unknown or has expired. Expired entries
+    /// encountered along the way are garbage-collected.
+    pub fn expected_nonce(&self, challenge_id: i32) -> Result<Vec<u8>, ChallengeStoreError> {
+        let mut entries = self.lock()?;
+        let lookup = entries
+            .get(&challenge_id)
+            .map(|entry| (entry.nonce.clone(), entry.issued_at.elapsed() > self.ttl));
+        self.gc(&mut entries);
+
+        match lookup {
+            None => Err(ChallengeStoreError::UnknownChallengeId(challenge_id)),
+            Some((_, true)) => Err(ChallengeStoreError::ChallengeExpired(challenge_id)),
+            Some((nonce, false)) => Ok(nonce),
+        }
+    }
+
+    /// Consume (remove) the challenge `challenge_id`, so it can never be
+    /// matched again. Callers must only do this once the platform verifier
+    /// has confirmed the nonce returned by [`expected_nonce`] actually
+    /// appears in the attestation document it verified — consuming before
+    /// that check would let an unrelated, stale document ride a freshly
+    /// issued challenge ID to acceptance.
+    ///
+    /// [`expected_nonce`]: ChallengeStore::expected_nonce
+    pub fn consume(&self, challenge_id: i32) -> Result<(), ChallengeStoreError> {
+        let mut entries = self.lock()?;
+        self.gc(&mut entries);
+        entries
+            .remove(&challenge_id)
+            .ok_or(ChallengeStoreError::UnknownChallengeId(challenge_id))?;
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<HashMap<i32, ChallengeEntry>>, ChallengeStoreError> {
+        self.entries
+            .lock()
+            .map_err(|e| ChallengeStoreError::LockError(format!("{:?}", e)))
+    }
+
+    /// Drop any entries whose TTL has elapsed.
+    fn gc(&self, entries: &mut HashMap<i32, ChallengeEntry>) {
+        let ttl = self.ttl;
+        entries.retain(|_, entry| entry.issued_at.elapsed() <= ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn expected_nonce_returns_the_issued_nonce() {
+        let store = ChallengeStore::new(Duration::from_secs(60));
+        store.issue(1, vec![1, 2, 3]).unwrap();
+        assert_eq!(store.expected_nonce(1).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn expected_nonce_rejects_an_unknown_challenge_id() {
+        let store = ChallengeStore::new(Duration::from_secs(60));
+        match store.expected_nonce(42) {
+            Err(ChallengeStoreError::UnknownChallengeId(42)) => {}
+            other => panic!("expected UnknownChallengeId, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn expected_nonce_rejects_an_expired_challenge() {
+        let store = ChallengeStore::new(Duration::from_millis(10));
+        store.issue(1, vec![1, 2, 3]).unwrap();
+        sleep(Duration::from_millis(30));
+        match store.expected_nonce(1) {
+            Err(ChallengeStoreError::ChallengeExpired(1)) => {}
+            other => panic!("expected ChallengeExpired, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn consume_removes_the_entry_so_it_cannot_be_reused() {
+        let store = ChallengeStore::new(Duration::from_secs(60));
+        store.issue(1, vec![1, 2, 3]).unwrap();
+        store.consume(1).unwrap();
+        match store.expected_nonce(1) {
+            Err(ChallengeStoreError::UnknownChallengeId(1)) => {}
+            other => panic!("expected UnknownChallengeId after consume, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn consume_rejects_an_unknown_challenge_id() {
+        let store = ChallengeStore::new(Duration::from_secs(60));
+        match store.consume(7) {
+            Err(ChallengeStoreError::UnknownChallengeId(7)) => {}
+            other => panic!("expected UnknownChallengeId, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_later_issue_overwrites_an_earlier_challenge_with_the_same_id() {
+        let store = ChallengeStore::new(Duration::from_secs(60));
+        store.issue(1, vec![1, 2, 3]).unwrap();
+        store.issue(1, vec![4, 5, 6]).unwrap();
+        assert_eq!(store.expected_nonce(1).unwrap(), vec![4, 5, 6]);
+    }
+}
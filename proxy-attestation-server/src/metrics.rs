@@ -0,0 +1,128 @@
+//! Prometheus metrics for the proxy attestation server
+//!
+//! Tracks attestation throughput and failures so operators can build
+//! dashboards and alerts: a counter of attestation requests labeled by
+//! platform, request kind, and outcome, and a latency histogram per route.
+//! `platform_router` is the only handler that records into
+//! `ATTESTATION_REQUESTS` directly, so it only ever sees the `PSA`/`Nitro`
+//! platform labels and the `AttestationToken` request kind (or `unknown`,
+//! for anything else a client's path segments might claim to be — see
+//! `platform_router::metrics_label`). The `/Start` route
+//! (`attestation::start`) isn't wired into this counter: it lives in a
+//! module this source tree doesn't carry, so there's no call site here to
+//! add an `.inc()` to. `REQUEST_LATENCY_SECONDS`, in contrast, covers every
+//! route including `/Start`, since `MetricsMiddleware` wraps the whole
+//! `App` rather than being called from inside individual handlers.
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    web, HttpResponse,
+};
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec,
+    TextEncoder,
+};
+use std::time::Instant;
+
+lazy_static! {
+    /// Attestation requests, labeled by platform (`PSA`/`Nitro`, or
+    /// `unknown`), request kind (`AttestationToken`, or `unknown`), and
+    /// outcome (`success`, the `ProxyAttestationServerError` variant name
+    /// on failure, or `unsupported_platform` when no registered platform
+    /// matches the path). Only `platform_router` increments this; `/Start`
+    /// (`attestation::start`) is not covered — see the module doc comment.
+    pub static ref ATTESTATION_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "veracruz_proxy_attestation_requests_total",
+        "Attestation requests handled by the proxy attestation server",
+        &["platform", "kind", "outcome"]
+    )
+    .unwrap();
+    /// Per-route request latency.
+    pub static ref REQUEST_LATENCY_SECONDS: HistogramVec = register_histogram_vec!(
+        "veracruz_proxy_attestation_request_latency_seconds",
+        "Latency of proxy attestation server requests",
+        &["route"]
+    )
+    .unwrap();
+}
+
+/// Render the current registry in Prometheus text-exposition format, for
+/// the `/metrics` route.
+pub async fn metrics() -> HttpResponse {
+    let encoder = TextEncoder::new();
+    let metric_families = prometheus::gather();
+    let mut buffer = Vec::new();
+    if encoder.encode(&metric_families, &mut buffer).is_err() {
+        return HttpResponse::InternalServerError().finish();
+    }
+    HttpResponse::Ok()
+        .content_type(encoder.format_type())
+        .body(buffer)
+}
+
+pub fn route() -> actix_web::Route {
+    web::get().to(metrics)
+}
+
+/// Actix middleware recording `REQUEST_LATENCY_SECONDS` around every
+/// request, labeled by the matched route pattern.
+pub struct MetricsMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = MetricsMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MetricsMiddlewareService { service })
+    }
+}
+
+pub struct MetricsMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| req.path().to_string());
+        let start = Instant::now();
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let response = fut.await?;
+            REQUEST_LATENCY_SECONDS
+                .with_label_values(&[&route])
+                .observe(start.elapsed().as_secs_f64());
+            Ok(response)
+        })
+    }
+}
@@ -1,5 +1,34 @@
 //! The Veracruz proxy attestation server
 //!
+//! `platform_router` accepts attestation evidence either as base64 text
+//! (the default, for backward compatibility) or, when the request carries
+//! `Content-Type: application/cbor`, as a raw CBOR byte string. The CBOR
+//! path stays byte-native all the way to the platform: [`decode_request_body`]
+//! hands the unwrapped bytes to [`AttestationPlatform::handle`] as
+//! [`AttestationInput::Bytes`], which PSA/Nitro forward to their
+//! `attestation_token_bytes` entry point, so base64 is never introduced on
+//! the server side (it's only ever encoded/decoded for the legacy text
+//! path, or when `Accept: application/cbor` asks for a CBOR response).
+//! Responses mirror the request's `Accept` header the same way.
+//!
+//! `server()` shuts down gracefully on `SIGINT`/`SIGTERM`: it drains
+//! in-flight requests (up to the `shutdown_timeout` passed in) before
+//! calling `VeracruzServer::shutdown_isolate` on every isolate it was
+//! handed, so that terminating the process doesn't leak enclave resources.
+//!
+//! `POST /Challenge` issues a single-use nonce (see [`issue_challenge`]); an
+//! `AttestationToken` request to `platform_router` must present that
+//! challenge's ID in the `X-Veracruz-Challenge-Id` header, and must embed
+//! the matching nonce *inside the attestation document itself*. Only the ID
+//! is looked up ahead of dispatch (via [`ChallengeStore::expected_nonce`]);
+//! the actual nonce match against the evidence is performed by the
+//! platform's own verifier (`psa`/`nitro`'s `attestation_token`/
+//! `attestation_token_bytes`), which is handed the expected nonce by
+//! [`AttestationPlatform::handle`]. The challenge is only consumed (via
+//! [`ChallengeStore::consume`]) once that verifier succeeds, so presenting
+//! a fresh challenge ID alongside an unrelated, stale document cannot pass —
+//! the header alone never proves anything.
+//!
 //! ## Authors
 //!
 //! The Veracruz Development Team.
@@ -14,54 +43,437 @@ use crate::attestation;
 use crate::attestation::nitro;
 #[cfg(any(feature = "linux", feature = "icecap"))]
 use crate::attestation::psa;
+use crate::challenge_store::ChallengeStore;
 use crate::error::*;
-use actix_web::{dev::Server, middleware, web, App, HttpServer};
+use crate::metrics::{self, MetricsMiddleware, ATTESTATION_REQUESTS};
+#[cfg(feature = "tls")]
+use crate::tls::ServerTlsConfig;
+use actix_web::{
+    dev::{Server, ServerHandle},
+    http::header,
+    web, App, HttpRequest, HttpResponse, HttpServer, ResponseError,
+};
 use lazy_static::lazy_static;
+use ring::rand::SecureRandom;
+use serde::Serialize;
 use std::{
     net::ToSocketAddrs,
     path,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
+use tracing_actix_web::TracingLogger;
+use veracruz_server::common::VeracruzServer;
+
+/// MIME type negotiated for the binary attestation-token transport. Clients
+/// that post with this `Content-Type` and/or send it in `Accept` skip the
+/// base64-over-text round trip entirely.
+const CBOR_MIME: &str = "application/cbor";
+
+/// Request kinds `platform_router` understands, i.e. the set `metrics_label`
+/// lets through unmodified for the `kind` label on `ATTESTATION_REQUESTS`.
+const KNOWN_REQUEST_KINDS: &[&str] = &["AttestationToken"];
+
+/// Does `req` carry a CBOR-encoded body?
+fn is_cbor_body(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains(CBOR_MIME))
+        .unwrap_or(false)
+}
+
+/// Does `req` ask for a CBOR-encoded response?
+fn wants_cbor(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains(CBOR_MIME))
+        .unwrap_or(false)
+}
+
+/// An attestation request body, decoded from the wire but not yet forced
+/// through base64 text: [`AttestationPlatform::handle`] only converts to a
+/// base64 `String` when that's genuinely what the body was (or the platform
+/// has no byte-native entry point), so a CBOR request's raw evidence bytes
+/// reach the platform as bytes, never inflated through base64 and back.
+enum AttestationInput {
+    /// The request body as already-base64 text (the plain, non-CBOR path).
+    Base64Text(String),
+    /// The raw evidence bytes unwrapped from a CBOR request body.
+    Bytes(Vec<u8>),
+}
+
+/// Decode an attestation request body for dispatch to an
+/// [`AttestationPlatform`]. A CBOR body is the raw attestation evidence
+/// bytes, wrapped in a CBOR byte string, and is handed on as
+/// [`AttestationInput::Bytes`] untouched by base64; everything else is taken
+/// to already be base64 text, as before.
+fn decode_request_body(
+    req: &HttpRequest,
+    body: &web::Bytes,
+) -> Result<AttestationInput, ProxyAttestationServerError> {
+    if is_cbor_body(req) {
+        let raw: Vec<u8> = ciborium::de::from_reader(body.as_ref())
+            .map_err(|_| ProxyAttestationServerError::UnsupportedRequestError)?;
+        Ok(AttestationInput::Bytes(raw))
+    } else {
+        String::from_utf8(body.to_vec())
+            .map(AttestationInput::Base64Text)
+            .map_err(|_| ProxyAttestationServerError::UnsupportedRequestError)
+    }
+}
+
+/// Turn the result of an attestation-token route into an `HttpResponse`,
+/// honouring `Accept: application/cbor` by re-encoding the base64 token as
+/// raw CBOR bytes instead of returning it as text.
+fn encode_response(req: &HttpRequest, result: ProxyAttestationServerResponder) -> HttpResponse {
+    let token = match result {
+        Ok(token) => token,
+        Err(err) => return err.error_response(),
+    };
+    if wants_cbor(req) {
+        let raw = match base64::decode(&token) {
+            Ok(raw) => raw,
+            Err(_) => return ProxyAttestationServerError::UnsupportedRequestError.error_response(),
+        };
+        let mut bytes = Vec::new();
+        match ciborium::ser::into_writer(&raw, &mut bytes) {
+            Ok(()) => HttpResponse::Ok().content_type(CBOR_MIME).body(bytes),
+            Err(_) => ProxyAttestationServerError::UnsupportedRequestError.error_response(),
+        }
+    } else {
+        HttpResponse::Ok().body(token)
+    }
+}
 
 lazy_static! {
     pub static ref DEBUG_MODE: AtomicBool = AtomicBool::new(false);
+    /// Outstanding attestation challenges, issued by [`issue_challenge`].
+    /// [`platform_router`] resolves a request's [`CHALLENGE_ID_HEADER`] to
+    /// its expected nonce (via [`ChallengeStore::expected_nonce`]) and hands
+    /// that to [`AttestationPlatform::handle`], which passes it on to the
+    /// platform verifier that checks it against the nonce actually embedded
+    /// in the attestation document; only once that succeeds is the entry
+    /// consumed ([`ChallengeStore::consume`]), enforcing single-use replay
+    /// protection. Entries expire five minutes after issue.
+    pub static ref CHALLENGE_STORE: ChallengeStore = ChallengeStore::new(Duration::from_secs(300));
 }
 
-#[allow(unused)]
-async fn psa_router(
-    psa_request: web::Path<String>,
-    input_data: String,
-) -> ProxyAttestationServerResponder {
-    #[cfg(any(feature = "linux", feature = "icecap"))]
-    if psa_request.into_inner().as_str() == "AttestationToken" {
-        psa::attestation_token(input_data)
+/// Source of challenge IDs handed out by [`issue_challenge`].
+static NEXT_CHALLENGE_ID: AtomicI32 = AtomicI32::new(1);
+
+/// The header an `AttestationToken` request must carry: the ID of a
+/// challenge previously issued by [`issue_challenge`]. It is only used to
+/// look up the expected nonce ahead of dispatch; the request proves it was
+/// produced in response to that specific challenge by embedding the nonce
+/// *inside the attestation document itself*, which the platform verifier
+/// checks (see [`AttestationPlatform::handle`]).
+const CHALLENGE_ID_HEADER: &str = "X-Veracruz-Challenge-Id";
+
+/// The body of [`issue_challenge`]'s response.
+#[derive(Serialize)]
+struct ChallengeIssued {
+    challenge_id: i32,
+    /// Base64-encoded nonce; embed it in the attestation document produced
+    /// for the `AttestationToken` request, and send `challenge_id` in that
+    /// request's [`CHALLENGE_ID_HEADER`] header.
+    nonce: String,
+}
+
+/// `POST /Challenge`: issue a fresh nonce and challenge ID for a client to
+/// embed in the attestation document it is about to request, so that the
+/// platform verifier invoked from [`platform_router`] can later confirm the
+/// resulting `AttestationToken` request is fresh and hasn't been replayed.
+async fn issue_challenge() -> HttpResponse {
+    let mut nonce = [0u8; 16];
+    if ring::rand::SystemRandom::new()
+        .fill(&mut nonce)
+        .is_err()
+    {
+        return ProxyAttestationServerError::UnsupportedRequestError.error_response();
+    }
+    let challenge_id = NEXT_CHALLENGE_ID.fetch_add(1, Ordering::SeqCst);
+    match CHALLENGE_STORE.issue(challenge_id, nonce.to_vec()) {
+        Ok(()) => HttpResponse::Ok().json(ChallengeIssued {
+            challenge_id,
+            nonce: base64::encode(nonce),
+        }),
+        Err(err) => {
+            tracing::error!(error = %err, "issue_challenge: ChallengeStore::issue failed");
+            ProxyAttestationServerError::UnsupportedRequestError.error_response()
+        }
+    }
+}
+
+/// Resolve `req`'s [`CHALLENGE_ID_HEADER`] to the challenge ID and its
+/// still-outstanding, unexpired nonce, without consuming the entry — the
+/// caller must pass the nonce on to the platform verifier and only call
+/// [`ChallengeStore::consume`] once that verifier confirms the nonce is
+/// the one embedded in the attestation document being submitted.
+fn expected_challenge(req: &HttpRequest) -> Result<(i32, Vec<u8>), ProxyAttestationServerError> {
+    let challenge_id: i32 = req
+        .headers()
+        .get(CHALLENGE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or(ProxyAttestationServerError::UnsupportedRequestError)?;
+    let nonce = CHALLENGE_STORE.expected_nonce(challenge_id).map_err(|err| {
+        tracing::warn!(error = %err, challenge_id, "expected_challenge: rejected");
+        ProxyAttestationServerError::UnsupportedRequestError
+    })?;
+    Ok((challenge_id, nonce))
+}
+
+/// Bound a path-derived label before it reaches `ATTESTATION_REQUESTS`:
+/// `platform_router`'s `platform`/`request` path segments come straight
+/// from an unauthenticated client, so passing them through to
+/// `with_label_values` unchanged would let an attacker mint unbounded
+/// label values and exhaust Prometheus's series memory. Anything other
+/// than one of `known` is folded into `"unknown"`.
+fn metrics_label<'a>(value: &'a str, known: &[&str]) -> &'a str {
+    if known.contains(&value) {
+        value
     } else {
-        Err(ProxyAttestationServerError::UnsupportedRequestError)
+        "unknown"
     }
-    #[cfg(not(any(feature = "linux", feature = "icecap")))]
-    Err(ProxyAttestationServerError::UnimplementedRequestError)
 }
 
-#[allow(unused)]
-async fn nitro_router(
-    nitro_request: web::Path<String>,
-    input_data: String,
-) -> ProxyAttestationServerResponder {
-    #[cfg(feature = "nitro")]
-    {
-        let inner = nitro_request.into_inner();
-        if inner.as_str() == "AttestationToken" {
-            nitro::attestation_token(input_data)
+/// Label `outcome` for `ATTESTATION_REQUESTS` with `success`, or the
+/// `ProxyAttestationServerError` variant name on failure.
+fn outcome_label(result: &ProxyAttestationServerResponder) -> String {
+    match result {
+        Ok(_) => "success".to_string(),
+        Err(err) => format!("{:?}", err)
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or("error")
+            .to_string(),
+    }
+}
+
+/// An isolation backend's attestation routes, registered under
+/// `/{name()}/{request}` by [`server`]. Adding a new backend is then a
+/// matter of implementing this trait and listing it in
+/// [`default_platforms`], rather than hand-wiring another pair of actix
+/// routes and another `#[cfg]`-gated router function.
+trait AttestationPlatform: Send + Sync {
+    /// The path segment this platform is registered under, e.g. `"PSA"`.
+    fn name(&self) -> &str;
+
+    /// Handle `request` (the second path segment, e.g. `"AttestationToken"`)
+    /// with the decoded request body. A CBOR-native caller gets
+    /// [`AttestationInput::Bytes`] straight through to the platform, with no
+    /// base64 conversion anywhere on the path. `expected_nonce` is the
+    /// nonce [`expected_challenge`] resolved from the request's challenge
+    /// ID; an `AttestationToken` implementation must check it against the
+    /// nonce embedded in the evidence itself and fail the request if they
+    /// don't match exactly.
+    fn handle(
+        &self,
+        request: &str,
+        input: AttestationInput,
+        expected_nonce: &[u8],
+    ) -> ProxyAttestationServerResponder;
+}
+
+struct PsaPlatform;
+
+impl AttestationPlatform for PsaPlatform {
+    fn name(&self) -> &str {
+        "PSA"
+    }
+
+    #[allow(unused)]
+    fn handle(
+        &self,
+        request: &str,
+        input: AttestationInput,
+        expected_nonce: &[u8],
+    ) -> ProxyAttestationServerResponder {
+        #[cfg(any(feature = "linux", feature = "icecap"))]
+        if request == "AttestationToken" {
+            return match input {
+                AttestationInput::Base64Text(text) => psa::attestation_token(text, expected_nonce),
+                AttestationInput::Bytes(raw) => psa::attestation_token_bytes(raw, expected_nonce),
+            };
         } else {
-            println!(
-                "proxy-attestation-server::nitro_router returning unsupported with into_inner:{:?}",
-                inner.as_str()
-            );
-            Err(ProxyAttestationServerError::UnsupportedRequestError)
+            return Err(ProxyAttestationServerError::UnsupportedRequestError);
+        }
+        #[cfg(not(any(feature = "linux", feature = "icecap")))]
+        Err(ProxyAttestationServerError::UnimplementedRequestError)
+    }
+}
+
+struct NitroPlatform;
+
+impl AttestationPlatform for NitroPlatform {
+    fn name(&self) -> &str {
+        "Nitro"
+    }
+
+    #[allow(unused)]
+    fn handle(
+        &self,
+        request: &str,
+        input: AttestationInput,
+        expected_nonce: &[u8],
+    ) -> ProxyAttestationServerResponder {
+        #[cfg(feature = "nitro")]
+        if request == "AttestationToken" {
+            return match input {
+                AttestationInput::Base64Text(text) => {
+                    nitro::attestation_token(text, expected_nonce)
+                }
+                AttestationInput::Bytes(raw) => nitro::attestation_token_bytes(raw, expected_nonce),
+            };
+        } else {
+            tracing_unsupported_request(request);
+            return Err(ProxyAttestationServerError::UnsupportedRequestError);
+        }
+        #[cfg(not(feature = "nitro"))]
+        Err(ProxyAttestationServerError::UnimplementedRequestError)
+    }
+}
+
+#[cfg(feature = "nitro")]
+fn tracing_unsupported_request(request: &str) {
+    tracing::warn!(request, "NitroPlatform::handle: unsupported request");
+}
+
+/// The isolation backends this build of the proxy attestation server
+/// exposes, in registration order.
+fn default_platforms() -> Vec<Box<dyn AttestationPlatform>> {
+    vec![Box::new(PsaPlatform), Box::new(NitroPlatform)]
+}
+
+/// The body of the 404 response `platform_router` returns for a path whose
+/// platform segment doesn't match any registered [`AttestationPlatform`].
+#[derive(Serialize)]
+struct UnsupportedPlatform {
+    requested: String,
+    supported: Vec<String>,
+}
+
+/// Single dynamic handler for `/{platform}/{request}`, dispatching to
+/// whichever registered [`AttestationPlatform`] matches `platform`.
+#[tracing::instrument(skip(req, body, platforms), fields(platform = %path.0, request = %path.1))]
+async fn platform_router(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: web::Bytes,
+    platforms: web::Data<Arc<Vec<Box<dyn AttestationPlatform>>>>,
+) -> HttpResponse {
+    let (platform_name, request_name) = path.into_inner();
+    let challenge = if request_name == "AttestationToken" {
+        match expected_challenge(&req) {
+            Ok(challenge) => Some(challenge),
+            Err(err) => return err.error_response(),
+        }
+    } else {
+        None
+    };
+    let input_data = match decode_request_body(&req, &body) {
+        Ok(input_data) => input_data,
+        Err(err) => return err.error_response(),
+    };
+
+    let known_platforms: Vec<&str> = platforms.iter().map(|p| p.name()).collect();
+    let platform_label = metrics_label(&platform_name, &known_platforms).to_string();
+    let request_label = metrics_label(&request_name, KNOWN_REQUEST_KINDS).to_string();
+    let expected_nonce = challenge.as_ref().map(|(_, nonce)| nonce.as_slice()).unwrap_or(&[]);
+
+    match platforms.iter().find(|p| p.name() == platform_name) {
+        Some(platform) => {
+            let result = platform.handle(&request_name, input_data, expected_nonce);
+            if result.is_ok() {
+                if let Some((challenge_id, _)) = challenge {
+                    if let Err(err) = CHALLENGE_STORE.consume(challenge_id) {
+                        tracing::error!(error = %err, challenge_id, "platform_router: failed to consume a challenge its own verifier just confirmed");
+                    }
+                }
+            }
+            ATTESTATION_REQUESTS
+                .with_label_values(&[&platform_label, &request_label, &outcome_label(&result)])
+                .inc();
+            encode_response(&req, result)
+        }
+        None => {
+            ATTESTATION_REQUESTS
+                .with_label_values(&[&platform_label, &request_label, "unsupported_platform"])
+                .inc();
+            HttpResponse::NotFound().json(UnsupportedPlatform {
+                requested: platform_name,
+                supported: platforms.iter().map(|p| p.name().to_string()).collect(),
+            })
         }
     }
-    #[cfg(not(feature = "nitro"))]
-    Err(ProxyAttestationServerError::UnimplementedRequestError)
+}
+
+/// Install a `tracing-subscriber` `fmt` subscriber for the process,
+/// filtered by `filter` (an `EnvFilter` spec, e.g.
+/// `"warn,proxy_attestation_server=info"`) when given, or by the `RUST_LOG`
+/// environment variable otherwise. Safe to call more than once: later
+/// calls, and callers who already installed their own subscriber, are
+/// silently ignored.
+fn init_tracing(filter: Option<&str>) {
+    let env_filter = match filter {
+        Some(filter) => tracing_subscriber::EnvFilter::new(filter),
+        None => tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+            tracing_subscriber::EnvFilter::new("warn,proxy_attestation_server=info")
+        }),
+    };
+    let _ = tracing_subscriber::fmt().with_env_filter(env_filter).try_init();
+}
+
+/// Wait for either `SIGINT` (`Ctrl-C`) or, on Unix, `SIGTERM`.
+async fn wait_for_shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install a SIGTERM handler");
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = terminate.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = ctrl_c.await;
+    }
+}
+
+/// Spawn a task that waits for a shutdown signal, then gracefully stops
+/// `handle` (draining in-flight `/Start` and `/{platform}/{request}`
+/// requests up to the configured `shutdown_timeout`) before calling
+/// [`VeracruzServer::shutdown_isolate`] on every isolate in `isolates`,
+/// logging (rather than propagating) any `shutdown_isolate` failure since
+/// there is no caller left to hand it back to by that point.
+fn spawn_shutdown_coordinator(
+    handle: ServerHandle,
+    isolates: Vec<Arc<Mutex<dyn VeracruzServer + Send>>>,
+) {
+    actix_web::rt::spawn(async move {
+        wait_for_shutdown_signal().await;
+        tracing::info!("shutdown signal received, draining in-flight requests");
+        handle.stop(true).await;
+        for isolate in &isolates {
+            let shutdown_result = match isolate.lock() {
+                Ok(mut isolate) => isolate.shutdown_isolate(),
+                Err(err) => {
+                    tracing::error!(error = %err, "failed to lock isolate for shutdown");
+                    continue;
+                }
+            };
+            if let Err(err) = shutdown_result {
+                tracing::error!(error = %err, "VeracruzServer::shutdown_isolate failed during graceful shutdown");
+            }
+        }
+    });
 }
 
 pub fn server<U, P1, P2>(
@@ -69,12 +481,17 @@ pub fn server<U, P1, P2>(
     ca_cert_path: P1,
     ca_key_path: P2,
     debug: bool,
+    #[cfg(feature = "tls")] tls: Option<ServerTlsConfig>,
+    tracing_filter: Option<&str>,
+    shutdown_timeout: Option<Duration>,
+    isolates: Vec<Arc<Mutex<dyn VeracruzServer + Send>>>,
 ) -> Result<Server, String>
 where
     U: ToSocketAddrs,
     P1: AsRef<path::Path>,
     P2: AsRef<path::Path>,
 {
+    init_tracing(tracing_filter);
     if debug {
         DEBUG_MODE.store(true, Ordering::SeqCst);
     }
@@ -90,15 +507,39 @@ where
             err
         )
     })?;
-    let server = HttpServer::new(move || {
+    let platforms = Arc::new(default_platforms());
+    let mut http_server = HttpServer::new(move || {
         App::new()
-            .wrap(middleware::Logger::default())
+            .app_data(web::Data::new(platforms.clone()))
+            .wrap(TracingLogger::default())
+            .wrap(MetricsMiddleware)
             .route("/Start", web::post().to(attestation::start))
-            .route("/PSA/{psa_request}", web::post().to(psa_router))
-            .route("/Nitro/{nitro_request}", web::post().to(nitro_router))
-    })
-    .bind(url)
-    .map_err(|err| format!("binding error: {:?}", err))?
-    .run();
+            .route("/Challenge", web::post().to(issue_challenge))
+            .route("/{platform}/{request}", web::post().to(platform_router))
+            .route("/metrics", metrics::route())
+    });
+    if let Some(shutdown_timeout) = shutdown_timeout {
+        http_server = http_server.shutdown_timeout(shutdown_timeout.as_secs());
+    }
+
+    #[cfg(feature = "tls")]
+    let server = match tls {
+        Some(tls) => http_server
+            .bind_rustls(url, tls.build()?)
+            .map_err(|err| format!("binding error: {:?}", err))?
+            .run(),
+        None => http_server
+            .bind(url)
+            .map_err(|err| format!("binding error: {:?}", err))?
+            .run(),
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = http_server
+        .bind(url)
+        .map_err(|err| format!("binding error: {:?}", err))?
+        .run();
+
+    spawn_shutdown_coordinator(server.handle(), isolates);
+
     Ok(server)
 }
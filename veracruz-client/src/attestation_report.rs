@@ -0,0 +1,169 @@
+//! Signed attestation reports carried in the runtime-hash certificate
+//! extension
+//!
+//! `compare_runtime_hash` used to just byte-compare the extension's
+//! contents against the platform hashes in the policy, trusting that
+//! whoever minted the peer certificate put the right bytes there. This
+//! module adds the option (selected per platform by the policy carrying an
+//! `attestation_ca_cert`) of treating the extension as a *signed*
+//! attestation report instead: a report body (the enclave measurement plus
+//! a signing timestamp) and a signature over it, verified against a pinned
+//! attestation-CA certificate, mirroring the SGX mutual-RA IAS
+//! report-signing-certificate pattern.
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use crate::error::VeracruzClientError;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The body of a signed attestation report: the enclave measurement the
+/// report attests to, and the RFC3339 timestamp at which it was signed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttestationReportBody {
+    pub measurement: Vec<u8>,
+    pub timestamp: String,
+}
+
+/// A report body together with the attestation-CA's signature over its
+/// bincode-serialized bytes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignedAttestationReport {
+    pub body: AttestationReportBody,
+    pub signature: Vec<u8>,
+}
+
+/// Verify `report`'s signature against `ca_cert`, check that its timestamp
+/// is within `freshness_window` of now, and return its measurement.
+///
+/// Distinct error variants are returned for each failure mode so that
+/// callers can tell a forged signature apart from a stale-but-genuine
+/// report.
+pub fn verify_signed_report(
+    report: &SignedAttestationReport,
+    ca_cert: &mbedtls::x509::Certificate,
+    freshness_window: Duration,
+) -> Result<Vec<u8>, VeracruzClientError> {
+    let body_bytes = bincode::serialize(&report.body)
+        .map_err(|_| VeracruzClientError::AttestationReportSignatureError)?;
+
+    let mut public_key = ca_cert.public_key().clone();
+    let mut hash = [0u8; 32];
+    mbedtls::hash::Md::hash(mbedtls::hash::Type::Sha256, &body_bytes, &mut hash)
+        .map_err(|_| VeracruzClientError::AttestationReportSignatureError)?;
+    public_key
+        .verify(
+            mbedtls::hash::Type::Sha256,
+            &hash,
+            &report.signature,
+        )
+        .map_err(|_| VeracruzClientError::AttestationReportSignatureError)?;
+
+    check_freshness(&report.body.timestamp, freshness_window)?;
+
+    Ok(report.body.measurement.clone())
+}
+
+/// Check that `timestamp` (an RFC3339 string) is within `freshness_window`
+/// of now. Split out from `verify_signed_report` so this date-math logic
+/// is unit-testable without needing a genuinely valid signature to reach
+/// it.
+fn check_freshness(timestamp: &str, freshness_window: Duration) -> Result<(), VeracruzClientError> {
+    let signed_at: DateTime<Utc> = timestamp
+        .parse()
+        .map_err(|_| VeracruzClientError::StaleAttestationReportError)?;
+    if Utc::now().signed_duration_since(signed_at) > freshness_window {
+        return Err(VeracruzClientError::StaleAttestationReportError);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A real self-signed EC (P-256) certificate, generated with `openssl`
+    /// purely for this test, so `verify_signed_report`'s signature check
+    /// runs against a genuinely parseable `mbedtls::x509::Certificate`
+    /// rather than a stub.
+    const TEST_CA_CERT_PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBkTCCATegAwIBAgIULCBn9MvC2bfsjpa2asuiZZJwb/IwCgYIKoZIzj0EAwIw\n\
+HjEcMBoGA1UEAwwTdGVzdC1hdHRlc3RhdGlvbi1jYTAeFw0yNjA3MzAwMzE2MTZa\n\
+Fw0zNjA3MjcwMzE2MTZaMB4xHDAaBgNVBAMME3Rlc3QtYXR0ZXN0YXRpb24tY2Ew\n\
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASbAdsB8E65duOr85yDip28zOk/Fqir\n\
+pS/E8Cl3Aopf+xN+qD+PsoxO+3oeIoeBvc/sIRc04FRe+wr6o5OI95h9o1MwUTAd\n\
+BgNVHQ4EFgQUEnG1IMKi0L5C3rXKh26WKtN21wwwHwYDVR0jBBgwFoAUEnG1IMKi\n\
+0L5C3rXKh26WKtN21wwwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBF\n\
+AiAzaGbQ1KRtA8L/NU3sRqVQDBModLznaUddGGv9/8265AIhAP/vp9p1SStjKPed\n\
+UJsw+8eMjEsS71v0ZQJLenteyZTp\n\
+-----END CERTIFICATE-----\n\0";
+
+    #[test]
+    fn check_freshness_accepts_a_recent_timestamp() {
+        let now = Utc::now().to_rfc3339();
+        assert!(check_freshness(&now, Duration::seconds(60)).is_ok());
+    }
+
+    #[test]
+    fn check_freshness_rejects_a_timestamp_older_than_the_window() {
+        let old = (Utc::now() - Duration::seconds(120)).to_rfc3339();
+        match check_freshness(&old, Duration::seconds(60)) {
+            Err(VeracruzClientError::StaleAttestationReportError) => {}
+            other => panic!("expected StaleAttestationReportError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_freshness_rejects_an_unparseable_timestamp() {
+        match check_freshness("not-a-timestamp", Duration::seconds(60)) {
+            Err(VeracruzClientError::StaleAttestationReportError) => {}
+            other => panic!("expected StaleAttestationReportError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn signed_attestation_report_round_trips_its_measurement_through_bincode() {
+        let report = SignedAttestationReport {
+            body: AttestationReportBody {
+                measurement: vec![9, 9, 9],
+                timestamp: Utc::now().to_rfc3339(),
+            },
+            signature: vec![0u8; 4],
+        };
+        let bytes = bincode::serialize(&report).unwrap();
+        let decoded: SignedAttestationReport = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.body.measurement, vec![9, 9, 9]);
+    }
+
+    #[test]
+    fn wrong_signature_is_rejected_against_a_real_ca_certificate() {
+        // Exercising the signature check end-to-end with a genuinely
+        // valid signature would require reproducing the exact ASN.1 form
+        // `mbedtls`'s ECDSA verify expects; lacking that, this exercises
+        // the real failure path with a real, `openssl`-generated
+        // self-signed EC certificate and a signature that cannot possibly
+        // match it, mirroring nitro_verify.rs's own
+        // `wrong_signature_is_rejected` precedent.
+        let ca_cert = mbedtls::x509::Certificate::from_pem_multiple(TEST_CA_CERT_PEM.as_bytes())
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+        let report = SignedAttestationReport {
+            body: AttestationReportBody {
+                measurement: vec![1, 2, 3],
+                timestamp: Utc::now().to_rfc3339(),
+            },
+            signature: vec![0u8; 64],
+        };
+        let err = verify_signed_report(&report, &ca_cert, Duration::seconds(60)).unwrap_err();
+        assert!(matches!(err, VeracruzClientError::AttestationReportSignatureError));
+    }
+}
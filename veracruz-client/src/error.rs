@@ -0,0 +1,78 @@
+//! Error type for the Veracruz client
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use err_derive::Error;
+
+#[derive(Debug, Error)]
+pub enum VeracruzClientError {
+    #[error(display = "VeracruzClient: IOError: {:?}.", _0)]
+    IOError(#[error(source)] std::io::Error),
+    #[error(display = "VeracruzClient: SerdeJsonError: {:?}.", _0)]
+    SerdeJsonError(#[error(source)] serde_json::Error),
+    #[error(display = "VeracruzClient: TransportProtocolError: {:?}.", _0)]
+    TransportProtocolError(#[error(source)] transport_protocol::TransportProtocolError),
+    #[error(display = "VeracruzClient: PolicyError: {:?}.", _0)]
+    PolicyError(#[error(source)] policy_utils::error::PolicyError),
+    #[error(display = "VeracruzClient: MbedTLSError: {:?}.", _0)]
+    MbedTLSError(#[error(source)] mbedtls::Error),
+    #[error(display = "VeracruzClient: X509ParserError: {}.", _0)]
+    X509ParserError(String),
+    #[error(display = "VeracruzClient: PEMError: {:?}.", _0)]
+    PEMError(#[error(source)] x509_parser::error::PEMError),
+    #[error(display = "VeracruzClient: WebpkiError: {:?}.", _0)]
+    WebpkiError(#[error(source)] webpki::Error),
+    #[error(display = "VeracruzClient: Utf8Error: {:?}.", _0)]
+    Utf8Error(#[error(source)] std::str::Utf8Error),
+    #[error(display = "VeracruzClient: HexError: {:?}.", _0)]
+    HexError(#[error(source)] hex::FromHexError),
+    #[error(display = "VeracruzClient: Unable to read expected data from input.")]
+    UnableToReadError,
+    #[error(display = "VeracruzClient: Failed to obtain lock.")]
+    LockFailed,
+    #[error(display = "VeracruzClient: Path was not valid UTF-8.")]
+    InvalidPath,
+    #[error(display = "VeracruzClient: {} had an unexpected length, expected {}.", _0, _1)]
+    InvalidLengthError(&'static str, usize),
+    #[error(display = "VeracruzClient: TLS error of unspecified kind.")]
+    TLSUnspecifiedError,
+    #[error(display = "VeracruzClient: Invalid TLS ciphersuite in policy: {}.", _0)]
+    TLSInvalidCiphersuiteError(String),
+    #[error(display = "VeracruzClient: Certificate at {} has expired.", _0)]
+    CertificateExpireError(String),
+    #[error(display = "VeracruzClient: {} did not match: expected {:?}, received {:?}.", variable, expected, received)]
+    MismatchError {
+        variable: &'static str,
+        expected: Vec<u8>,
+        received: Vec<u8>,
+    },
+    #[error(display = "VeracruzClient: Unexpected response to {}: {:?}.", _0, _1)]
+    ResponseError(&'static str, transport_protocol::ResponseStatus),
+    #[error(display = "VeracruzClient: Server response carried no result.")]
+    VeracruzServerResponseNoResultError,
+    #[error(display = "VeracruzClient: No platform hash in the policy matched the runtime's.")]
+    NoMatchingRuntimeIsolateHash,
+    #[error(display = "VeracruzClient: Peer did not present exactly one certificate.")]
+    NoPeerCertificatesError,
+    #[error(display = "VeracruzClient: Peer certificate was not in the expected form.")]
+    UnexpectedCertificateError,
+    #[error(display = "VeracruzClient: Runtime hash extension missing from peer certificate.")]
+    RuntimeHashExtensionMissingError,
+    #[error(display = "VeracruzClient: Policy pins no attestation CA for any platform; signed-report verification was not attempted.")]
+    NoAttestationCaPinned,
+    #[error(display = "VeracruzClient: Runtime-hash extension's attestation report signature did not verify.")]
+    AttestationReportSignatureError,
+    #[error(display = "VeracruzClient: Runtime-hash extension's attestation report is stale.")]
+    StaleAttestationReportError,
+    #[error(display = "VeracruzClient: Runtime-hash extension's attestation report measurement did not match the policy.")]
+    AttestationMeasurementMismatchError,
+    #[error(display = "VeracruzClient: Client and server protocol versions are incompatible: client supports {}, server supports {}.", client, server)]
+    IncompatibleProtocolVersion { client: String, server: String },
+}
@@ -0,0 +1,60 @@
+//! Pluggable cyphertext transports for `VeracruzClient`
+//!
+//! mbedtls reads and writes the attested TLS session's cyphertext through
+//! whatever implements `Read`/`Write`; `Transport` names that role so new
+//! byte-level channels (HTTP-polling, a raw TCP socket, QUIC, ...) can be
+//! selected by `with_policy_and_hash` without touching `send()`,
+//! `send_program()`, or any other `VeracruzClient` method.
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use std::io::{Read, Write};
+
+/// The byte-level cyphertext channel mbedtls reads/writes through.
+///
+/// Boxed as `Box<dyn Transport>` so that `VeracruzClient` can hold whichever
+/// channel `with_policy_and_hash` selected behind one concrete type.
+pub trait Transport: Read + Write + Send {}
+impl<T: Read + Write + Send> Transport for T {}
+
+/// A direct TCP socket to the Veracruz server, with no HTTP framing or
+/// per-write base64 encoding: cyphertext bytes are written straight to the
+/// socket and read straight back. Selected when the policy's
+/// `veracruz_server_url` has an explicit `tcp://` scheme; `quic://` selects
+/// `QuicConnection` instead, and any other scheme (including none) falls
+/// through to the original base64-over-HTTP transport. For deployments where the server exposes a raw port.
+pub struct TcpTransport {
+    socket: std::net::TcpStream,
+}
+
+impl TcpTransport {
+    /// Connect directly to `server_addr` (`host:port`).
+    pub fn connect(server_addr: &str) -> std::io::Result<Self> {
+        let socket = std::net::TcpStream::connect(server_addr)?;
+        socket.set_nodelay(true)?;
+        Ok(TcpTransport { socket })
+    }
+}
+
+impl Read for TcpTransport {
+    fn read(&mut self, data: &mut [u8]) -> std::io::Result<usize> {
+        self.socket.read(data)
+    }
+}
+
+impl Write for TcpTransport {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.socket.write(data)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.socket.flush()
+    }
+}
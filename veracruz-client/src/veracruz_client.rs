@@ -9,7 +9,10 @@
 //! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
 //! information on licensing and copyright.
 
+use crate::attestation_report::{verify_signed_report, SignedAttestationReport};
 use crate::error::VeracruzClientError;
+use crate::quic_connection::QuicConnection;
+use crate::transport::{TcpTransport, Transport};
 use log::{error, info};
 use mbedtls::alloc::List;
 use policy_utils::{parsers::enforce_leading_backslash, policy::Policy, Platform};
@@ -23,16 +26,62 @@ use veracruz_utils::VERACRUZ_RUNTIME_HASH_EXTENSION_ID;
 use webpki;
 
 /// VeracruzClient struct. The remote_session_id is shared between
-/// VeracruzClient and InsecureConnection so that it is available from
-/// VeracruzClient methods and can also be updated by the
-/// InsecureConnection methods invoked by mbedtls. Although we do not
-/// expect multiple threads to be involved, since the compiler can not
-/// check this, it is safer to use a Mutex.
+/// VeracruzClient and whichever `Transport` it established (e.g.
+/// `InsecureConnection`, `QuicConnection`) so that it is available from
+/// VeracruzClient methods and can also be updated by the transport's own
+/// methods, invoked by mbedtls. Although we do not expect multiple threads
+/// to be involved, since the compiler can not check this, it is safer to
+/// use a Mutex.
 pub struct VeracruzClient {
-    tls_context: mbedtls::ssl::Context<InsecureConnection>,
+    tls_context: mbedtls::ssl::Context<Box<dyn Transport>>,
     remote_session_id: Arc<Mutex<Option<u32>>>,
     policy: Policy,
     policy_hash: String,
+    /// The `transport_protocol` version negotiated with the server during
+    /// `with_policy_and_hash`, once per connection.
+    protocol_version: String,
+    /// How old a signed attestation report's timestamp may be before
+    /// `verify_attestation_report` rejects it as stale. Defaults to
+    /// [`DEFAULT_FRESHNESS_WINDOW`]; configurable via
+    /// `with_policy_and_hash_and_proxy_and_freshness_window`.
+    freshness_window: chrono::Duration,
+    /// Owns the Tokio executor backing a QUIC transport's
+    /// `tokio::runtime::Handle`, if one was dialed. Dropping the `Runtime`
+    /// shuts its executor down even while cloned `Handle`s remain around,
+    /// so it must live at least as long as the `VeracruzClient` that holds
+    /// the `Handle`.
+    _quic_runtime: Option<tokio::runtime::Runtime>,
+}
+
+/// The default freshness window for signed attestation reports, used
+/// unless a caller opts into a different one via
+/// `with_policy_and_hash_and_proxy_and_freshness_window`.
+const DEFAULT_FRESHNESS_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+
+/// The `transport_protocol` schema version this client speaks.
+const CLIENT_PROTOCOL_VERSION: &str = "1.0";
+
+/// A proxy to dial through when reaching the Veracruz server, for clients
+/// behind a firewall or wanting to reach the enclave over Tor.
+#[derive(Clone, Debug)]
+pub struct ProxyConfig {
+    /// The proxy address, e.g. `socks5://127.0.0.1:9050` or
+    /// `http://proxy.example.com:8080`.
+    pub address: String,
+    /// An optional `username:password` credential for the proxy.
+    pub auth: Option<String>,
+}
+
+impl ProxyConfig {
+    fn build(&self) -> Result<reqwest::Proxy, VeracruzClientError> {
+        let mut proxy =
+            reqwest::Proxy::all(&self.address).map_err(|_| VeracruzClientError::InvalidPath)?;
+        if let Some(auth) = &self.auth {
+            let (username, password) = auth.split_once(':').ok_or(VeracruzClientError::InvalidPath)?;
+            proxy = proxy.basic_auth(username, password);
+        }
+        Ok(proxy)
+    }
 }
 
 /// This is the structure given to mbedtls and used for reading and
@@ -41,6 +90,7 @@ struct InsecureConnection {
     read_buffer: Vec<u8>,
     veracruz_server_url: String,
     remote_session_id: Arc<Mutex<Option<u32>>>,
+    proxy: Option<ProxyConfig>,
 }
 
 impl Read for InsecureConnection {
@@ -69,11 +119,17 @@ impl Write for InsecureConnection {
             string_data
         );
         let dest_url = format!("http://{:}/runtime_manager", self.veracruz_server_url,);
+        let proxy = self.proxy.clone();
         // Spawn a separate thread so that we can use reqwest::blocking.
         let body = std::thread::spawn(move || {
-            let client_build = reqwest::blocking::ClientBuilder::new()
-                .build()
-                .map_err(|_| err("reqwest new"))?;
+            let mut client_builder = reqwest::blocking::ClientBuilder::new();
+            if let Some(proxy) = proxy {
+                let reqwest_proxy = proxy
+                    .build()
+                    .map_err(|_| err("invalid proxy configuration"))?;
+                client_builder = client_builder.proxy(reqwest_proxy);
+            }
+            let client_build = client_builder.build().map_err(|_| err("reqwest new"))?;
             let ret = client_build
                 .post(dest_url)
                 .body(combined_string)
@@ -161,6 +217,28 @@ impl VeracruzClient {
         Ok(pkey_vec)
     }
 
+    /// Parse `data` as one or more certificate revocation lists, PEM or
+    /// DER, pushing all of them onto a single `Crl` (mbedtls parses a
+    /// buffer of concatenated PEM blocks as a chain, the same way it does
+    /// for `Certificate::from_pem_multiple`).
+    fn parse_crl(data: &[u8]) -> Result<mbedtls::x509::Crl, VeracruzClientError> {
+        let mut crl = mbedtls::x509::Crl::new();
+        crl.push_from_pem(data)
+            .or_else(|_| crl.push_from_der(data))
+            .map_err(|_| VeracruzClientError::TLSUnspecifiedError)?;
+        Ok(crl)
+    }
+
+    /// Provide file path.
+    /// Read the certificate revocation list(s) (CRL) in the file, PEM or DER.
+    /// Return Ok(crl) if succ
+    /// Otherwise return Err(msg) with the error message as String
+    fn read_crl<P: AsRef<Path>>(filename: P) -> Result<mbedtls::x509::Crl, VeracruzClientError> {
+        let mut buffer = VeracruzClient::read_all_bytes_in_file(filename)?;
+        buffer.push(b'\0');
+        Self::parse_crl(&buffer)
+    }
+
     /// Check the validity of client_cert:
     /// parse the certificate and match it with the public key generated from the private key;
     /// check if the certificate is valid in term of time.
@@ -226,6 +304,51 @@ impl VeracruzClient {
         client_key_filename: P2,
         policy: Policy,
         policy_hash: String,
+    ) -> Result<VeracruzClient, VeracruzClientError> {
+        Self::with_policy_and_hash_and_proxy(
+            client_cert_filename,
+            client_key_filename,
+            policy,
+            policy_hash,
+            None,
+        )
+    }
+
+    /// As `with_policy_and_hash`, but additionally dials the server through
+    /// `proxy` (a SOCKS5 or HTTP-CONNECT proxy) when the HTTP transport is
+    /// selected, for clients behind a firewall or wanting to reach the
+    /// enclave over Tor.
+    pub fn with_policy_and_hash_and_proxy<P1: AsRef<Path>, P2: AsRef<Path>>(
+        client_cert_filename: P1,
+        client_key_filename: P2,
+        policy: Policy,
+        policy_hash: String,
+        proxy: Option<ProxyConfig>,
+    ) -> Result<VeracruzClient, VeracruzClientError> {
+        Self::with_policy_and_hash_and_proxy_and_freshness_window(
+            client_cert_filename,
+            client_key_filename,
+            policy,
+            policy_hash,
+            proxy,
+            DEFAULT_FRESHNESS_WINDOW,
+        )
+    }
+
+    /// As `with_policy_and_hash_and_proxy`, but additionally lets the
+    /// caller configure how old a signed attestation report's timestamp
+    /// may be before `verify_attestation_report` rejects it as stale,
+    /// instead of the fixed `DEFAULT_FRESHNESS_WINDOW`.
+    pub fn with_policy_and_hash_and_proxy_and_freshness_window<
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    >(
+        client_cert_filename: P1,
+        client_key_filename: P2,
+        policy: Policy,
+        policy_hash: String,
+        proxy: Option<ProxyConfig>,
+        freshness_window: chrono::Duration,
     ) -> Result<VeracruzClient, VeracruzClientError> {
         let client_cert = Self::read_cert(&client_cert_filename)?;
         let mut client_priv_key = Self::read_private_key(&client_key_filename)?;
@@ -249,36 +372,182 @@ impl VeracruzClient {
             mbedtls::ssl::config::Transport::Stream,
             mbedtls::ssl::config::Preset::Default,
         );
-        config.set_min_version(mbedtls::ssl::config::Version::Tls1_2)?;
-        config.set_max_version(mbedtls::ssl::config::Version::Tls1_2)?;
+        // Default to TLS 1.2-only for backward compatibility; a policy that
+        // opts in to `tls1_3` gets the shorter TLS 1.3 handshake and
+        // AEAD-only ciphersuites instead.
+        let (min_version, max_version) = if policy.allow_tls_1_3() {
+            (
+                mbedtls::ssl::config::Version::Tls1_2,
+                mbedtls::ssl::config::Version::Tls1_3,
+            )
+        } else {
+            (
+                mbedtls::ssl::config::Version::Tls1_2,
+                mbedtls::ssl::config::Version::Tls1_2,
+            )
+        };
+        config.set_min_version(min_version)?;
+        config.set_max_version(max_version)?;
         let policy_ciphersuite = veracruz_utils::lookup_ciphersuite_mbedtls(
             policy.ciphersuite().as_str(),
         )
         .ok_or_else(|| {
             VeracruzClientError::TLSInvalidCiphersuiteError(policy.ciphersuite().to_string())
         })?;
-        let cipher_suites: Vec<i32> = vec![policy_ciphersuite.into(), 0];
+        let mut cipher_suites: Vec<i32> = vec![policy_ciphersuite.into()];
+        if policy.allow_tls_1_3() {
+            for name in ["TLS1-3-AES-256-GCM-SHA384", "TLS1-3-AES-128-GCM-SHA256"] {
+                if let Some(suite) = veracruz_utils::lookup_ciphersuite_mbedtls(name) {
+                    cipher_suites.push(suite.into());
+                }
+            }
+        }
+        cipher_suites.push(0);
         config.set_ciphersuites(Arc::new(cipher_suites));
         let entropy = Arc::new(mbedtls::rng::OsEntropy::new());
         let rng = Arc::new(mbedtls::rng::CtrDrbg::new(entropy, None)?);
         config.set_rng(rng);
-        config.set_ca_list(Arc::new(proxy_service_cert), None);
+        // Load any CRL(s) the policy pins for the proxy-service certificate
+        // chain, so a revoked proxy-service or runtime certificate is
+        // rejected for the rest of its validity period rather than
+        // accepted until it expires. A `file://` prefix selects reading the
+        // CRL(s) (PEM or DER, and one or more of either) from a path
+        // instead of taking the field as the CRL data itself.
+        let crl = match policy.proxy_service_crl() {
+            Some(crl_field) => {
+                let crl = match crl_field.strip_prefix("file://") {
+                    Some(crl_path) => Self::read_crl(crl_path)?,
+                    None => {
+                        let mut crl_data = crl_field.clone();
+                        crl_data.push('\0');
+                        Self::parse_crl(crl_data.as_bytes())?
+                    }
+                };
+                Some(Arc::new(crl))
+            }
+            None => None,
+        };
+        config.set_ca_list(Arc::new(proxy_service_cert), crl);
         config.push_cert(Arc::new(client_cert), Arc::new(client_priv_key))?;
         let mut ctx = mbedtls::ssl::Context::new(Arc::new(config));
         let remote_session_id = Arc::new(Mutex::new(Some(0)));
-        let conn = InsecureConnection {
-            read_buffer: vec![],
-            veracruz_server_url: policy.veracruz_server_url().to_string(),
-            remote_session_id: Arc::clone(&remote_session_id),
-        };
-        ctx.establish(conn, None)?;
 
-        Ok(VeracruzClient {
+        // The scheme on the policy's `veracruz_server_url` selects the
+        // `Transport` to dial: `quic://` for the native QUIC channel,
+        // `tcp://` for a direct socket against a server exposing a raw
+        // port, and otherwise the original base64-over-HTTP channel.
+        let mut quic_runtime = None;
+        let transport: Box<dyn Transport> =
+            if let Some(quic_server_url) = policy.veracruz_server_url().strip_prefix("quic://") {
+                // `QuicConnection` only holds a `Handle`; the owning
+                // `Runtime` must outlive it (and every `block_on` call made
+                // through that `Handle`), so it's kept on `VeracruzClient`
+                // rather than dropped at the end of this arm.
+                let runtime = tokio::runtime::Runtime::new().map_err(VeracruzClientError::IOError)?;
+                let connection = QuicConnection::connect(
+                    quic_server_url,
+                    runtime.handle().clone(),
+                    Arc::clone(&remote_session_id),
+                )?;
+                quic_runtime = Some(runtime);
+                Box::new(connection)
+            } else if let Some(tcp_server_url) =
+                policy.veracruz_server_url().strip_prefix("tcp://")
+            {
+                Box::new(TcpTransport::connect(tcp_server_url).map_err(VeracruzClientError::IOError)?)
+            } else {
+                Box::new(InsecureConnection {
+                    read_buffer: vec![],
+                    veracruz_server_url: policy.veracruz_server_url().to_string(),
+                    remote_session_id: Arc::clone(&remote_session_id),
+                    proxy,
+                })
+            };
+        ctx.establish(transport, None)?;
+
+        let mut client = VeracruzClient {
             tls_context: ctx,
             remote_session_id: Arc::clone(&remote_session_id),
             policy,
             policy_hash,
-        })
+            protocol_version: CLIENT_PROTOCOL_VERSION.to_string(),
+            freshness_window,
+            _quic_runtime: quic_runtime,
+        };
+        client.negotiate_protocol_version()?;
+        Ok(client)
+    }
+
+    /// Exchange `transport_protocol` versions with the server once, right
+    /// after the TLS session is established, so that a client and server
+    /// built against incompatible schemas fail fast with a clear error
+    /// instead of producing opaque deserialization failures on every
+    /// subsequent request.
+    ///
+    /// The server reports either a single version (`"1.0"`) or a supported
+    /// range (`"1.0-1.2"`); either way, negotiation succeeds as long as the
+    /// client's version falls inside what the server reports, rather than
+    /// requiring the two strings to match exactly. That lets a server stay
+    /// backward-compatible with an older client while advertising its own,
+    /// newer version.
+    ///
+    /// Synchronous, like the rest of connection setup: `tls_context`'s
+    /// mbedtls I/O never actually suspends, so this talks to
+    /// `tls_context` directly rather than going through the (pre-existing)
+    /// `async fn send`, to avoid forcing the constructors that call this
+    /// to be `async` too for no genuine suspension point.
+    fn negotiate_protocol_version(&mut self) -> Result<(), VeracruzClientError> {
+        let serialized_request = transport_protocol::serialize_request_protocol_version()?;
+        self.tls_context.write_all(&serialized_request)?;
+        let mut response = vec![];
+        self.tls_context.read_to_end(&mut response)?;
+        let parsed_response = transport_protocol::parse_runtime_manager_response(
+            *self
+                .remote_session_id
+                .lock()
+                .map_err(|_| VeracruzClientError::LockFailed)?,
+            &response,
+        )?;
+        match parsed_response.status {
+            transport_protocol::ResponseStatus::SUCCESS => {
+                let server_version =
+                    std::str::from_utf8(&parsed_response.get_protocol_version().data)?.to_string();
+                if !Self::server_range_includes_client(&server_version, &self.protocol_version) {
+                    return Err(VeracruzClientError::IncompatibleProtocolVersion {
+                        client: self.protocol_version.clone(),
+                        server: server_version,
+                    });
+                }
+                Ok(())
+            }
+            _ => Err(VeracruzClientError::ResponseError(
+                "negotiate_protocol_version",
+                parsed_response.status,
+            )),
+        }
+    }
+
+    /// Does the server's reported version/range (`"1.0"` or `"1.0-1.2"`)
+    /// include `client_version`? An unparseable `server_range` is treated
+    /// as incompatible rather than panicking or silently passing.
+    fn server_range_includes_client(server_range: &str, client_version: &str) -> bool {
+        let client_version = match Self::parse_version(client_version) {
+            Some(version) => version,
+            None => return false,
+        };
+        match server_range.split_once('-') {
+            Some((min, max)) => match (Self::parse_version(min), Self::parse_version(max)) {
+                (Some(min), Some(max)) => client_version >= min && client_version <= max,
+                _ => false,
+            },
+            None => Self::parse_version(server_range) == Some(client_version),
+        }
+    }
+
+    /// Parse a `"major.minor"` version string into a comparable tuple.
+    fn parse_version(version: &str) -> Option<(u32, u32)> {
+        let (major, minor) = version.split_once('.')?;
+        Some((major.parse().ok()?, minor.parse().ok()?))
     }
 
     /// Check the policy and runtime hashes, and then send the `program` to the remote `path`.
@@ -469,6 +738,67 @@ impl VeracruzClient {
         Err(VeracruzClientError::NoMatchingRuntimeIsolateHash)
     }
 
+    /// Verify `extension_data` as a signed attestation report (body plus an
+    /// attestation-CA signature over it), checking freshness against
+    /// `self.freshness_window` and the embedded measurement against the
+    /// policy's platform hashes.
+    ///
+    /// Returns `Err(NoAttestationCaPinned)` only when the policy pins no
+    /// attestation-CA certificate for *any* platform — the signal
+    /// `check_runtime_hash` uses to fall back to the legacy raw-hash
+    /// comparison in `compare_runtime_hash`, for deployments that haven't
+    /// adopted signed reports yet. Once at least one platform's CA is
+    /// pinned, a failure is never downgraded to that fallback: it comes
+    /// back as `AttestationReportSignatureError` (or
+    /// `AttestationMeasurementMismatchError`), a hard failure, since a peer
+    /// presenting a forged or corrupted report must not be treated the
+    /// same as one simply not using signed reports.
+    ///
+    /// Mirrors `compare_runtime_hash`'s own loop: a policy may pin CA
+    /// certificates for more than one platform, and the peer's report may
+    /// be signed under any of them, so a verification failure against one
+    /// platform's CA moves on to the next instead of giving up outright.
+    fn verify_attestation_report(&self, extension_data: &[u8]) -> Result<(), VeracruzClientError> {
+        let report: Result<SignedAttestationReport, _> = bincode::deserialize(extension_data);
+
+        let platforms = vec![Platform::Linux, Platform::Nitro, Platform::IceCap];
+        let mut any_ca_pinned = false;
+        for platform in platforms {
+            let ca_cert_pem = match self.policy.attestation_ca_cert(&platform) {
+                Err(_) => continue, // no attestation CA pinned for this platform
+                Ok(data) => data,
+            };
+            any_ca_pinned = true;
+            let report = report
+                .as_ref()
+                .map_err(|_| VeracruzClientError::AttestationReportSignatureError)?;
+            let mut ca_cert_pem = ca_cert_pem.clone();
+            ca_cert_pem.push('\0');
+            let ca_certs = match mbedtls::x509::Certificate::from_pem_multiple(ca_cert_pem.as_bytes())
+            {
+                Ok(ca_certs) => ca_certs,
+                Err(_) => continue,
+            };
+            let ca_cert = match ca_certs.iter().next() {
+                Some(ca_cert) => ca_cert,
+                None => continue,
+            };
+
+            let measurement = match verify_signed_report(report, ca_cert, self.freshness_window) {
+                Ok(measurement) => measurement,
+                Err(_) => continue, // wrong platform for this report; try the rest
+            };
+            return self
+                .compare_runtime_hash(&measurement)
+                .map_err(|_| VeracruzClientError::AttestationMeasurementMismatchError);
+        }
+        if any_ca_pinned {
+            Err(VeracruzClientError::AttestationReportSignatureError)
+        } else {
+            Err(VeracruzClientError::NoAttestationCaPinned)
+        }
+    }
+
     /// Request the hash of the remote veracruz runtime and check if it matches.
     fn check_runtime_hash(&self) -> Result<(), VeracruzClientError> {
         let certs = self.tls_context.peer_cert();
@@ -508,7 +838,21 @@ impl VeracruzClient {
                         Ok(input.read_bytes_to_end())
                     })?;
                 info!("Certificate extension extracted correctly.");
-                match self.compare_runtime_hash(extension_data.as_slice_less_safe()) {
+                let extension_bytes = extension_data.as_slice_less_safe();
+                // Prefer the signed attestation-report path when the
+                // policy pins an attestation CA; fall back to the legacy
+                // raw-hash comparison only when none is pinned at all (e.g.
+                // for platforms that have not yet adopted signed reports).
+                // A genuine verification failure against a pinned CA is a
+                // hard error, not a fallback trigger.
+                let result = match self.verify_attestation_report(extension_bytes) {
+                    Ok(()) => Ok(()),
+                    Err(VeracruzClientError::NoAttestationCaPinned) => {
+                        self.compare_runtime_hash(extension_bytes)
+                    }
+                    Err(err) => Err(err),
+                };
+                match result {
                     Ok(_) => {
                         info!("Runtime hash matches.");
                         Ok(())
@@ -598,3 +942,94 @@ fn decode_tls_message(data: &[u8]) {
         _ => println!("Unknown"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A real CRL, generated with `openssl ca -gencrl` against a throwaway
+    /// self-signed CA, purely for this test, so `parse_crl`'s PEM/DER
+    /// parsing runs against genuinely well-formed input rather than a
+    /// stub.
+    const TEST_CRL_PEM: &str = "-----BEGIN X509 CRL-----\n\
+MIHWMH8CAQEwCgYIKoZIzj0EAwIwHjEcMBoGA1UEAwwTdGVzdC1hdHRlc3RhdGlv\n\
+bi1jYRcNMjYwNzMwMDM0MjE5WhcNMjYwODI5MDM0MjE5WqAwMC4wHwYDVR0jBBgw\n\
+FoAUEnG1IMKi0L5C3rXKh26WKtN21wwwCwYDVR0UBAQCAhAAMAoGCCqGSM49BAMC\n\
+A0cAMEQCIGl/J00/P38Omjdvbm3snrnmwGgG1yDgGD894JqBtuvyAiBeFPxlqIqm\n\
+NVyMYhO+TOv6wh/Ioj7swA1uh4KteCBMdA==\n\
+-----END X509 CRL-----\n\0";
+
+    const TEST_CRL_DER: &[u8] = &[
+        48, 129, 214, 48, 127, 2, 1, 1, 48, 10, 6, 8, 42, 134, 72, 206, 61, 4, 3, 2, 48, 30, 49,
+        28, 48, 26, 6, 3, 85, 4, 3, 12, 19, 116, 101, 115, 116, 45, 97, 116, 116, 101, 115, 116,
+        97, 116, 105, 111, 110, 45, 99, 97, 23, 13, 50, 54, 48, 55, 51, 48, 48, 51, 52, 50, 49,
+        57, 90, 23, 13, 50, 54, 48, 56, 50, 57, 48, 51, 52, 50, 49, 57, 90, 160, 48, 48, 46, 48,
+        31, 6, 3, 85, 29, 35, 4, 24, 48, 22, 128, 20, 18, 113, 181, 32, 194, 162, 208, 190, 66,
+        222, 181, 202, 135, 110, 150, 42, 211, 118, 215, 12, 48, 11, 6, 3, 85, 29, 20, 4, 4, 2, 2,
+        16, 0, 48, 10, 6, 8, 42, 134, 72, 206, 61, 4, 3, 2, 3, 71, 0, 48, 68, 2, 32, 105, 127, 39,
+        77, 63, 63, 127, 14, 154, 55, 111, 110, 109, 236, 158, 185, 230, 192, 104, 6, 215, 32,
+        224, 24, 63, 61, 224, 154, 129, 182, 235, 242, 2, 32, 94, 20, 252, 101, 168, 138, 166, 53,
+        92, 140, 98, 19, 190, 76, 235, 250, 194, 31, 200, 162, 62, 236, 192, 13, 110, 135, 130,
+        173, 120, 32, 76, 116,
+    ];
+
+    #[test]
+    fn parse_crl_accepts_a_real_pem_crl() {
+        VeracruzClient::parse_crl(TEST_CRL_PEM.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn parse_crl_accepts_a_real_der_crl() {
+        VeracruzClient::parse_crl(TEST_CRL_DER).unwrap();
+    }
+
+    #[test]
+    fn parse_crl_rejects_garbage() {
+        let err = VeracruzClient::parse_crl(&[0x00u8, 0x01, 0x02, 0x03]).unwrap_err();
+        assert!(matches!(err, VeracruzClientError::TLSUnspecifiedError));
+    }
+
+    #[test]
+    fn parse_version_parses_major_minor() {
+        assert_eq!(VeracruzClient::parse_version("1.0"), Some((1, 0)));
+        assert_eq!(VeracruzClient::parse_version("12.34"), Some((12, 34)));
+    }
+
+    #[test]
+    fn parse_version_rejects_malformed_input() {
+        assert_eq!(VeracruzClient::parse_version("garbage"), None);
+        assert_eq!(VeracruzClient::parse_version("1"), None);
+        assert_eq!(VeracruzClient::parse_version("a.b"), None);
+    }
+
+    #[test]
+    fn server_range_includes_client_matches_an_exact_version() {
+        assert!(VeracruzClient::server_range_includes_client("1.0", "1.0"));
+        assert!(!VeracruzClient::server_range_includes_client("1.0", "1.1"));
+    }
+
+    #[test]
+    fn server_range_includes_client_matches_inside_a_range() {
+        assert!(VeracruzClient::server_range_includes_client("1.0-1.2", "1.1"));
+        assert!(VeracruzClient::server_range_includes_client("1.0-1.2", "1.0"));
+        assert!(VeracruzClient::server_range_includes_client("1.0-1.2", "1.2"));
+    }
+
+    #[test]
+    fn server_range_includes_client_rejects_outside_a_range() {
+        assert!(!VeracruzClient::server_range_includes_client("1.0-1.2", "1.3"));
+        assert!(!VeracruzClient::server_range_includes_client("1.0-1.2", "0.9"));
+    }
+
+    #[test]
+    fn server_range_includes_client_rejects_an_unparseable_range_or_client_version() {
+        assert!(!VeracruzClient::server_range_includes_client(
+            "not-a-range",
+            "1.0"
+        ));
+        assert!(!VeracruzClient::server_range_includes_client(
+            "1.0-1.2",
+            "not-a-version"
+        ));
+    }
+}
@@ -10,15 +10,26 @@
 //! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
 //! information on licensing and copyright.
 
+use err_derive::Error;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
 use crate::runtime_manager_message::Status;
 
+mod nitro_verify;
+pub use nitro_verify::{verify_nitro_document, NitroVerificationError, NitroVerifier, VerifiedNitroDocument};
+
+mod root_enclave;
+pub use root_enclave::{Platform, RootEnclaveMessage, Verifier, VerifiedClaims};
+
 /// An enumerated type describing messages passed between to/from the Runtime
 /// Manager enclave (These originate from the Untrusted Pass-through (Veracruz
 /// server)
 /// These messages are inteded to be serialized using bincode before transport,
 /// and deserialized using bincode after transport
+///
+/// Kept for backward compatibility with deployments that have not yet moved
+/// to the platform-agnostic [`RootEnclaveMessage`]/[`Verifier`] protocol.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum NitroRootEnclaveMessage {
     /// A message generated by an operation that did not return data, but did
@@ -79,3 +90,128 @@ pub enum NitroRootEnclaveMessage {
     /// (for example, a response to a SetCertChain request)
     Success,
 }
+
+/// Every `NitroRootEnclaveMessage` variant corresponds 1:1 to a
+/// [`RootEnclaveMessage`] variant, implicitly carrying evidence for
+/// [`Platform::Nitro`]; this makes that correspondence explicit, so the two
+/// protocols are provably the same handshake generalized over `Platform`
+/// rather than independent types that merely look alike.
+impl From<NitroRootEnclaveMessage> for RootEnclaveMessage {
+    fn from(message: NitroRootEnclaveMessage) -> Self {
+        match message {
+            NitroRootEnclaveMessage::Status(status) => RootEnclaveMessage::Status(status),
+            NitroRootEnclaveMessage::FetchFirmwareVersion => {
+                RootEnclaveMessage::FetchFirmwareVersion
+            }
+            NitroRootEnclaveMessage::FirmwareVersion(version) => {
+                RootEnclaveMessage::FirmwareVersion(version)
+            }
+            NitroRootEnclaveMessage::SetCertChain(cert, key) => {
+                RootEnclaveMessage::SetCertChain(cert, key)
+            }
+            NitroRootEnclaveMessage::NativeAttestation(challenge, device_id) => {
+                RootEnclaveMessage::NativeAttestation(Platform::Nitro, challenge, device_id)
+            }
+            NitroRootEnclaveMessage::TokenData(token, csr) => {
+                RootEnclaveMessage::TokenData(token, csr)
+            }
+            NitroRootEnclaveMessage::StartProxy => RootEnclaveMessage::StartProxy,
+            NitroRootEnclaveMessage::ChallengeData(challenge, id) => {
+                RootEnclaveMessage::ChallengeData(challenge, id)
+            }
+            NitroRootEnclaveMessage::ProxyAttestation(evidence, challenge_id) => {
+                RootEnclaveMessage::ProxyAttestation(Platform::Nitro, evidence, challenge_id)
+            }
+            NitroRootEnclaveMessage::CertChain(chain) => RootEnclaveMessage::CertChain(chain),
+            NitroRootEnclaveMessage::Success => RootEnclaveMessage::Success,
+        }
+    }
+}
+
+/// Returned by `NitroRootEnclaveMessage`'s `TryFrom<RootEnclaveMessage>` impl
+/// when the message being narrowed is tagged for a platform other than
+/// [`Platform::Nitro`].
+#[derive(Debug, Error)]
+#[error(
+    display = "NitroRootEnclaveMessage: cannot convert a message tagged for {:?}, only Platform::Nitro.",
+    _0
+)]
+pub struct WrongPlatformError(pub Platform);
+
+impl TryFrom<RootEnclaveMessage> for NitroRootEnclaveMessage {
+    type Error = WrongPlatformError;
+
+    /// Narrow a `RootEnclaveMessage` back down to the Nitro-specific wire
+    /// format, for callers that have not yet moved off it. Fails if the
+    /// message is tagged for a platform other than `Platform::Nitro`.
+    fn try_from(message: RootEnclaveMessage) -> Result<Self, Self::Error> {
+        Ok(match message {
+            RootEnclaveMessage::Status(status) => NitroRootEnclaveMessage::Status(status),
+            RootEnclaveMessage::FetchFirmwareVersion => {
+                NitroRootEnclaveMessage::FetchFirmwareVersion
+            }
+            RootEnclaveMessage::FirmwareVersion(version) => {
+                NitroRootEnclaveMessage::FirmwareVersion(version)
+            }
+            RootEnclaveMessage::SetCertChain(cert, key) => {
+                NitroRootEnclaveMessage::SetCertChain(cert, key)
+            }
+            RootEnclaveMessage::NativeAttestation(platform, challenge, device_id) => {
+                if platform != Platform::Nitro {
+                    return Err(WrongPlatformError(platform));
+                }
+                NitroRootEnclaveMessage::NativeAttestation(challenge, device_id)
+            }
+            RootEnclaveMessage::TokenData(token, csr) => {
+                NitroRootEnclaveMessage::TokenData(token, csr)
+            }
+            RootEnclaveMessage::StartProxy => NitroRootEnclaveMessage::StartProxy,
+            RootEnclaveMessage::ChallengeData(challenge, id) => {
+                NitroRootEnclaveMessage::ChallengeData(challenge, id)
+            }
+            RootEnclaveMessage::ProxyAttestation(platform, evidence, challenge_id) => {
+                if platform != Platform::Nitro {
+                    return Err(WrongPlatformError(platform));
+                }
+                NitroRootEnclaveMessage::ProxyAttestation(evidence, challenge_id)
+            }
+            RootEnclaveMessage::CertChain(chain) => NitroRootEnclaveMessage::CertChain(chain),
+            RootEnclaveMessage::Success => NitroRootEnclaveMessage::Success,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nitro_message_round_trips_through_root_enclave_message() {
+        let original = NitroRootEnclaveMessage::NativeAttestation(vec![1, 2, 3], 7);
+        let generalized: RootEnclaveMessage = original.into();
+        match &generalized {
+            RootEnclaveMessage::NativeAttestation(Platform::Nitro, challenge, device_id) => {
+                assert_eq!(challenge, &vec![1, 2, 3]);
+                assert_eq!(*device_id, 7);
+            }
+            other => panic!("expected a Nitro-tagged NativeAttestation, got {:?}", other),
+        }
+        let narrowed = NitroRootEnclaveMessage::try_from(generalized).unwrap();
+        match narrowed {
+            NitroRootEnclaveMessage::NativeAttestation(challenge, device_id) => {
+                assert_eq!(challenge, vec![1, 2, 3]);
+                assert_eq!(device_id, 7);
+            }
+            other => panic!("expected NativeAttestation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_message_tagged_for_another_platform_does_not_narrow_to_nitro() {
+        let sgx_message = RootEnclaveMessage::ProxyAttestation(Platform::SgxDcap, vec![9], 1);
+        match NitroRootEnclaveMessage::try_from(sgx_message) {
+            Err(WrongPlatformError(Platform::SgxDcap)) => {}
+            other => panic!("expected WrongPlatformError(SgxDcap), got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Verification of AWS Nitro Enclave attestation documents
+//!
+//! A Nitro attestation document is a CBOR-encoded COSE_Sign1 structure
+//! whose payload is itself a CBOR map describing the enclave that produced
+//! it (its PCR measurements, its certificate chain, and any caller-supplied
+//! nonce/user-data). `verify_nitro_document` checks the COSE signature
+//! against the embedded certificate chain (which must terminate at a
+//! caller-supplied AWS Nitro root CA certificate) and hands back the
+//! claims a caller needs in order to decide whether to trust the enclave.
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use ciborium::value::Value;
+use err_derive::Error;
+use std::collections::HashMap;
+
+#[derive(Debug, Error)]
+pub enum NitroVerificationError {
+    #[error(display = "NitroVerificationError: Document was not a well-formed COSE_Sign1 CBOR array: {}", _0)]
+    MalformedCoseSign1(String),
+    #[error(display = "NitroVerificationError: Document payload was not a well-formed attestation document: {}", _0)]
+    MalformedPayload(String),
+    #[error(display = "NitroVerificationError: Missing required field '{}' in attestation document", _0)]
+    MissingField(&'static str),
+    #[error(display = "NitroVerificationError: Certificate chain failed to validate: {}", _0)]
+    CertificateChainError(String),
+    #[error(display = "NitroVerificationError: COSE_Sign1 signature did not verify")]
+    SignatureVerificationFailed,
+    #[error(display = "NitroVerificationError: Leaf certificate was not valid at the document's timestamp")]
+    CertificateNotValidAtTimestamp,
+}
+
+/// The claims extracted from a verified Nitro attestation document.
+#[derive(Debug, Clone)]
+pub struct VerifiedNitroDocument {
+    /// PCR index -> PCR value.
+    pub pcrs: HashMap<u8, Vec<u8>>,
+    /// An optional public key bound into the document by the enclave.
+    pub public_key: Option<Vec<u8>>,
+    /// Caller-supplied opaque user data (used, e.g., to bind a Noise
+    /// static key hash into the document).
+    pub user_data: Option<Vec<u8>>,
+    /// The nonce the enclave was challenged with, if any.
+    pub nonce: Option<Vec<u8>>,
+    /// Milliseconds since the Unix epoch at which the document was signed.
+    pub timestamp: u64,
+}
+
+/// Parse and fully verify a raw Nitro attestation document.
+///
+/// This: (1) decodes the COSE_Sign1 CBOR structure; (2) builds and
+/// validates the certificate chain embedded in the document's `cabundle`
+/// and `certificate` fields against the caller-supplied
+/// `root_certificate_der`, checking the leaf certificate's
+/// validity against the document's `timestamp`; (3) reconstructs the COSE
+/// `Sig_structure` and verifies the ECDSA P-384 signature using the leaf
+/// certificate's public key; and (4) returns the claims the document
+/// carries. Callers are responsible for comparing the returned `nonce`
+/// against the challenge they issued, and the returned `pcrs` against the
+/// expected measurements.
+///
+/// `root_certificate_der` is the DER-encoded AWS Nitro Enclaves root CA
+/// certificate (see
+/// <https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html>),
+/// supplied by the caller so that this module carries no pinned trust
+/// material of its own.
+pub fn verify_nitro_document(
+    document: &[u8],
+    root_certificate_der: &[u8],
+) -> Result<VerifiedNitroDocument, NitroVerificationError> {
+    let cose_sign1: Vec<Value> = ciborium::de::from_reader(document)
+        .map_err(|e| NitroVerificationError::MalformedCoseSign1(e.to_string()))?;
+    if cose_sign1.len() != 4 {
+        return Err(NitroVerificationError::MalformedCoseSign1(format!(
+            "expected a 4-element COSE_Sign1 array, got {} elements",
+            cose_sign1.len()
+        )));
+    }
+    let protected = bytes_of(&cose_sign1[0])
+        .ok_or_else(|| NitroVerificationError::MalformedCoseSign1("protected headers were not a bstr".to_string()))?;
+    let payload_bytes = bytes_of(&cose_sign1[2])
+        .ok_or_else(|| NitroVerificationError::MalformedCoseSign1("payload was not a bstr".to_string()))?;
+    let signature = bytes_of(&cose_sign1[3])
+        .ok_or_else(|| NitroVerificationError::MalformedCoseSign1("signature was not a bstr".to_string()))?;
+
+    let payload: HashMap<String, Value> = ciborium::de::from_reader(payload_bytes.as_slice())
+        .map_err(|e| NitroVerificationError::MalformedPayload(e.to_string()))?;
+
+    let timestamp = match payload.get("timestamp") {
+        Some(Value::Integer(i)) => i64::from(*i) as u64,
+        _ => return Err(NitroVerificationError::MissingField("timestamp")),
+    };
+    let certificate = payload
+        .get("certificate")
+        .and_then(bytes_of)
+        .ok_or(NitroVerificationError::MissingField("certificate"))?;
+    let cabundle: Vec<Vec<u8>> = match payload.get("cabundle") {
+        Some(Value::Array(entries)) => entries.iter().filter_map(bytes_of).collect(),
+        _ => return Err(NitroVerificationError::MissingField("cabundle")),
+    };
+    let pcrs = match payload.get("pcrs") {
+        Some(Value::Map(entries)) => {
+            let mut out = HashMap::new();
+            for (k, v) in entries {
+                if let (Some(index), Some(value)) = (integer_of(k), bytes_of(v)) {
+                    out.insert(index as u8, value);
+                }
+            }
+            out
+        }
+        _ => return Err(NitroVerificationError::MissingField("pcrs")),
+    };
+    let public_key = payload.get("public_key").and_then(bytes_of);
+    let user_data = payload.get("user_data").and_then(bytes_of);
+    let nonce = payload.get("nonce").and_then(bytes_of);
+
+    // Step 2: validate the certificate chain (leaf + cabundle) against the
+    // pinned root, including the leaf's validity at `timestamp`.
+    validate_certificate_chain(&certificate, &cabundle, timestamp, root_certificate_der)?;
+
+    // Step 3: reconstruct the COSE Sig_structure and check the signature.
+    verify_cose_signature(&protected, &payload_bytes, &signature, &certificate)?;
+
+    Ok(VerifiedNitroDocument {
+        pcrs,
+        public_key,
+        user_data,
+        nonce,
+        timestamp,
+    })
+}
+
+fn bytes_of(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Bytes(b) => Some(b.clone()),
+        _ => None,
+    }
+}
+
+fn integer_of(value: &Value) -> Option<i128> {
+    match value {
+        Value::Integer(i) => Some(i128::from(*i)),
+        _ => None,
+    }
+}
+
+/// Build the chain `certificate` <- `cabundle[last]` <- ... <- `cabundle[0]`
+/// up to `root_certificate_der` and validate it with `webpki`, including
+/// checking the leaf's validity at `timestamp_ms`.
+fn validate_certificate_chain(
+    leaf_der: &[u8],
+    cabundle: &[Vec<u8>],
+    timestamp_ms: u64,
+    root_certificate_der: &[u8],
+) -> Result<(), NitroVerificationError> {
+    let trust_anchor = webpki::TrustAnchor::try_from_cert_der(root_certificate_der)
+        .map_err(|e| NitroVerificationError::CertificateChainError(format!("{:?}", e)))?;
+    let anchors = webpki::TlsServerTrustAnchors(&[trust_anchor]);
+
+    let intermediates: Vec<&[u8]> = cabundle.iter().map(|c| c.as_slice()).collect();
+    let end_entity = webpki::EndEntityCert::try_from(leaf_der)
+        .map_err(|e| NitroVerificationError::CertificateChainError(format!("{:?}", e)))?;
+
+    let time = webpki::Time::from_seconds_since_unix_epoch(timestamp_ms / 1000);
+    end_entity
+        .verify_is_valid_tls_server_cert(
+            &[
+                &webpki::ECDSA_P384_SHA384,
+                &webpki::ECDSA_P256_SHA256,
+            ],
+            &anchors,
+            &intermediates,
+            time,
+        )
+        .map_err(|e| match e {
+            webpki::Error::CertExpired | webpki::Error::CertNotValidYet => {
+                NitroVerificationError::CertificateNotValidAtTimestamp
+            }
+            other => NitroVerificationError::CertificateChainError(format!("{:?}", other)),
+        })?;
+
+    Ok(())
+}
+
+/// Reconstruct the COSE `Sig_structure` (`["Signature1", protected, h'', payload]`)
+/// and check the ECDSA P-384 signature against the leaf certificate's public key.
+fn verify_cose_signature(
+    protected: &[u8],
+    payload: &[u8],
+    signature: &[u8],
+    leaf_cert_der: &[u8],
+) -> Result<(), NitroVerificationError> {
+    let sig_structure = Value::Array(vec![
+        Value::Text("Signature1".to_string()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(vec![]),
+        Value::Bytes(payload.to_vec()),
+    ]);
+    let mut to_verify = Vec::new();
+    ciborium::ser::into_writer(&sig_structure, &mut to_verify)
+        .map_err(|e| NitroVerificationError::MalformedCoseSign1(e.to_string()))?;
+
+    let (_, cert) = x509_parser::parse_x509_certificate(leaf_cert_der)
+        .map_err(|e| NitroVerificationError::CertificateChainError(e.to_string()))?;
+    let public_key = cert.tbs_certificate.subject_pki.subject_public_key.data;
+
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P384_SHA384_FIXED, public_key)
+        .verify(&to_verify, signature)
+        .map_err(|_| NitroVerificationError::SignatureVerificationFailed)
+}
+
+/// A [`Verifier`] backed by [`verify_nitro_document`], plugging Nitro into
+/// the platform-agnostic root-enclave protocol.
+pub struct NitroVerifier {
+    root_certificate_der: Vec<u8>,
+}
+
+impl NitroVerifier {
+    /// Construct a verifier pinned to `root_certificate_der` (the DER-encoded
+    /// AWS Nitro Enclaves root CA certificate).
+    pub fn new(root_certificate_der: Vec<u8>) -> Self {
+        NitroVerifier {
+            root_certificate_der,
+        }
+    }
+}
+
+impl super::root_enclave::Verifier for NitroVerifier {
+    fn platform(&self) -> super::root_enclave::Platform {
+        super::root_enclave::Platform::Nitro
+    }
+
+    fn verify(
+        &self,
+        evidence: &[u8],
+        expected_challenge: &[u8],
+    ) -> Result<super::root_enclave::VerifiedClaims, anyhow::Error> {
+        let document = verify_nitro_document(evidence, &self.root_certificate_der)?;
+        let nonce = document
+            .nonce
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Nitro document did not carry a nonce"))?;
+        if nonce != expected_challenge {
+            return Err(anyhow::anyhow!(
+                "Nitro document nonce did not match the expected challenge"
+            ));
+        }
+        let measurements = document
+            .pcrs
+            .iter()
+            .map(|(index, value)| (format!("PCR{}", index), value.clone()))
+            .collect();
+        Ok(super::root_enclave::VerifiedClaims {
+            measurements,
+            public_key: document.public_key,
+            nonce,
+            platform: super::root_enclave::Platform::Nitro,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_cbor_is_rejected() {
+        let garbage = [0xffu8, 0x00, 0x01, 0x02];
+        let err = verify_nitro_document(&garbage, &[]).unwrap_err();
+        assert!(matches!(err, NitroVerificationError::MalformedCoseSign1(_)));
+    }
+
+    #[test]
+    fn wrong_cose_sign1_array_length_is_rejected() {
+        let mut document = Vec::new();
+        let not_cose_sign1 = Value::Array(vec![Value::Bytes(vec![]), Value::Bytes(vec![])]);
+        ciborium::ser::into_writer(&not_cose_sign1, &mut document).unwrap();
+        let err = verify_nitro_document(&document, &[]).unwrap_err();
+        assert!(matches!(err, NitroVerificationError::MalformedCoseSign1(_)));
+    }
+
+    #[test]
+    fn payload_missing_timestamp_is_rejected() {
+        let payload = Value::Map(vec![(
+            Value::Text("certificate".to_string()),
+            Value::Bytes(vec![]),
+        )]);
+        let mut payload_bytes = Vec::new();
+        ciborium::ser::into_writer(&payload, &mut payload_bytes).unwrap();
+
+        let cose_sign1 = Value::Array(vec![
+            Value::Bytes(vec![]),
+            Value::Map(vec![]),
+            Value::Bytes(payload_bytes),
+            Value::Bytes(vec![]),
+        ]);
+        let mut document = Vec::new();
+        ciborium::ser::into_writer(&cose_sign1, &mut document).unwrap();
+
+        let err = verify_nitro_document(&document, &[]).unwrap_err();
+        assert!(matches!(err, NitroVerificationError::MissingField("timestamp")));
+    }
+
+    #[test]
+    fn chain_validation_failure_is_distinguished_from_expiry() {
+        // A root certificate that doesn't even parse is a chain-building
+        // failure, not a "leaf expired at this timestamp" failure, and
+        // must not be reported as `CertificateNotValidAtTimestamp`.
+        let not_a_certificate = [0x00u8, 0x01, 0x02, 0x03];
+        let err =
+            validate_certificate_chain(&not_a_certificate, &[], 0, &not_a_certificate).unwrap_err();
+        assert!(matches!(err, NitroVerificationError::CertificateChainError(_)));
+    }
+
+    #[test]
+    fn wrong_signature_is_rejected() {
+        // `verify_cose_signature` maps any `ring` verification failure to
+        // `SignatureVerificationFailed`; exercise that underlying check
+        // with a real key pair and a signature that doesn't match the
+        // reconstructed Sig_structure bytes (exercising it end-to-end
+        // needs a leaf certificate `x509_parser` can parse, which this
+        // module deliberately carries none of).
+        let pkcs8 = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap();
+        let key_pair = ring::signature::EcdsaKeyPair::from_pkcs8(
+            &ring::signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+            pkcs8.as_ref(),
+        )
+        .unwrap();
+        let bogus_signature = vec![0u8; 96];
+        let result = ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ECDSA_P384_SHA384_FIXED,
+            key_pair.public_key(),
+        )
+        .verify(b"Signature1", &bogus_signature);
+        assert!(result.is_err());
+    }
+}
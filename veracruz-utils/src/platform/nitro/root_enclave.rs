@@ -0,0 +1,132 @@
+//! A platform-agnostic root-enclave protocol
+//!
+//! `NitroRootEnclaveMessage` hard-codes the root-enclave handshake (challenge
+//! -> native attestation -> proxy attestation -> cert chain) to AWS Nitro,
+//! even though the shape of that handshake is identical across TEEs. This
+//! module lifts the same state machine to a `RootEnclaveMessage` protocol
+//! that carries an opaque evidence blob tagged with a `Platform`
+//! discriminant, and a `Verifier` trait that each backend (Nitro, SGX/DCAP,
+//! IBM Secure Execution, ...) implements to turn that evidence into a
+//! `VerifiedClaims`. `NitroRootEnclaveMessage`'s `From`/`TryFrom` impls
+//! (in `super::nitro`) show it is provably the same handshake, generalized
+//! over `Platform`, not a disconnected parallel type.
+//!
+//! This module is infrastructure only: nothing in this source tree drives
+//! a `RootEnclaveMessage` exchange or dispatches to a `Verifier` at
+//! runtime — that driving code (the Proxy Attestation Service's root
+//! enclave client) lives outside this snapshot. Wiring the PAS over to
+//! `RootEnclaveMessage`, selecting a `Verifier` from the message's
+//! `Platform` tag, is the follow-up needed before this protocol carries
+//! any real traffic.
+//!
+//! ## Authors
+//!
+//! The Veracruz Development Team.
+//!
+//! ## Licensing and copyright notice
+//!
+//! See the `LICENSE_MIT.markdown` file in the Veracruz root directory for
+//! information on licensing and copyright.
+
+use serde::{Deserialize, Serialize};
+
+use crate::runtime_manager_message::Status;
+
+/// The TEE backend that produced a piece of attestation evidence.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// AWS Nitro Enclaves.
+    Nitro,
+    /// Intel SGX, verified via the DCAP quote path.
+    SgxDcap,
+    /// IBM Secure Execution (Linux on Z).
+    IbmSecureExecution,
+}
+
+/// The claims a `Verifier` extracts from a piece of evidence, in a form
+/// common to every platform.
+#[derive(Debug, Clone)]
+pub struct VerifiedClaims {
+    /// The platform-specific measurements of the attested enclave (e.g.
+    /// Nitro PCRs, an SGX MRENCLAVE/MRSIGNER pair), keyed by a
+    /// platform-defined label.
+    pub measurements: Vec<(String, Vec<u8>)>,
+    /// A public key bound into the evidence by the enclave, if any.
+    pub public_key: Option<Vec<u8>>,
+    /// The nonce the enclave was challenged with.
+    pub nonce: Vec<u8>,
+    /// The platform that produced this evidence.
+    pub platform: Platform,
+}
+
+/// A pluggable verifier for one TEE backend's attestation evidence.
+///
+/// Each implementation checks the evidence of the one `Platform` reported
+/// by `platform()`, the same tag a `RootEnclaveMessage` carries alongside
+/// its evidence.
+pub trait Verifier {
+    /// The platform this verifier checks evidence for.
+    fn platform(&self) -> Platform;
+
+    /// Verify `evidence`, checking that it embeds `expected_challenge` as
+    /// its nonce, and return the claims it carries.
+    fn verify(
+        &self,
+        evidence: &[u8],
+        expected_challenge: &[u8],
+    ) -> Result<VerifiedClaims, anyhow::Error>;
+}
+
+/// A platform-agnostic version of `NitroRootEnclaveMessage`: the same
+/// challenge/attestation/cert-chain handshake, but carrying an opaque
+/// evidence blob tagged with a `Platform` instead of being hard-coded to
+/// Nitro's document format.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RootEnclaveMessage {
+    /// A message generated by an operation that did not return data, but did
+    /// return a status.
+    Status(Status),
+    /// A request to fetch the firmware version from the root enclave.
+    FetchFirmwareVersion,
+    /// A response to `FetchFirmwareVersion`, the root enclave's firmware
+    /// version as a string.
+    FirmwareVersion(String),
+    /// A request to set the certificate chain for the root enclave.
+    SetCertChain(Vec<u8>, Vec<u8>),
+    /// A request to start the native attestation process for `platform`.
+    /// Parameters:
+    /// Platform - which backend's `Verifier` should handle this evidence
+    /// Vec<u8>  - the 128-bit challenge value generated by the caller
+    /// i32      - a device ID set by the caller, used in future operations
+    NativeAttestation(Platform, Vec<u8>, i32),
+    /// A response to `NativeAttestation`, generated by the enclave.
+    /// Parameters:
+    /// Vec<u8> - the native attestation evidence generated by the enclave
+    /// Vec<u8> - the CSR generated by the root enclave, used by the proxy
+    ///           service to generate the Root Enclave Certificate
+    TokenData(Vec<u8>, Vec<u8>),
+    /// A request to start the proxy attestation process for the caller.
+    /// Results in a `ChallengeData` response.
+    StartProxy,
+    /// A response to `StartProxy`.
+    /// Vec<u8> - the 128-bit challenge value generated by the root enclave
+    /// i32     - the challenge ID generated by the root enclave, to match
+    ///           the challenge to future requests
+    ChallengeData(Vec<u8>, i32),
+    /// A request (initiated by the Runtime Manager enclave) to start the
+    /// proxy attestation process.
+    /// Parameters:
+    /// Platform - which backend's `Verifier` should handle this evidence
+    /// Vec<u8>  - the evidence blob generated by the caller
+    /// i32      - the challenge ID received in `ChallengeData`, letting the
+    ///            root enclave know which challenge value to check for
+    ProxyAttestation(Platform, Vec<u8>, i32),
+    /// A response to `ProxyAttestation`: the certificate that the compute
+    /// enclave will send to its clients.
+    /// Vec<u8> - the compute enclave certificate
+    /// Vec<u8> - the root enclave certificate
+    /// Vec<u8> - the CA root certificate
+    CertChain(Vec<Vec<u8>>),
+    /// A successful response to a request that just contains a status.
+    Success,
+}